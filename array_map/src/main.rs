@@ -1,18 +1,184 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
 use std::io::prelude::*;
 use std::io::{stdin, stdout};
+use std::str::FromStr;
 
-fn map_array(array: Vec<isize>, op: &str, n: isize) -> Vec<isize> {
+#[derive(Debug, PartialEq)]
+enum MapError {
+    Overflow(String),
+    EmptyArray(String),
+    InvalidOperation(String),
+    ZeroBucketSize,
+    BucketTooLarge(usize),
+    ElementOverflow { index: usize, value: isize },
+    FloatOverflow { index: usize, value: f64 },
+    DivisionByZero,
+    InvalidNumber(String),
+}
+
+impl Display for MapError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            MapError::Overflow(op) => write!(f, "The {} is too large", op),
+            MapError::EmptyArray(op) => write!(f, "Cannot take the {} of an empty array", op),
+            MapError::InvalidOperation(op) => write!(f, "Invalid operation {}", op),
+            MapError::ZeroBucketSize => write!(f, "Bucket size must be greater than zero"),
+            MapError::BucketTooLarge(size) => write!(f, "Bucket size {} is too large", size),
+            MapError::ElementOverflow { index, value } => write!(
+                f,
+                "Element {} ({}) overflows, the operation was not applied",
+                index + 1,
+                value
+            ),
+            MapError::FloatOverflow { index, value } => write!(
+                f,
+                "Element {} ({}) overflows, the operation was not applied",
+                index + 1,
+                value
+            ),
+            MapError::DivisionByZero => write!(f, "Cannot divide by zero"),
+            MapError::InvalidNumber(number) => write!(f, "Invalid number {}", number),
+        }
+    }
+}
+
+// Stops at the first element whose result doesn't fit in an isize rather
+// than leaving a wrapped value in the result.
+fn map_array(array: Vec<isize>, op: &str, n: isize) -> Result<Vec<isize>, MapError> {
+    let apply: fn(isize, isize) -> Option<isize> = match op {
+        "add" | "+" => isize::checked_add,
+        "sub" | "-" => isize::checked_sub,
+        "mul" | "*" => isize::checked_mul,
+        "div" | "/" if n == 0 => return Err(MapError::DivisionByZero),
+        "div" | "/" => isize::checked_div,
+        _ => return Err(MapError::InvalidOperation(op.to_string())),
+    };
     let mut result: Vec<isize> = Vec::new();
-    for &num in array.iter() {
-        match op {
-            "add" | "+" => result.push(num + n),
-            "sub" | "-" => result.push(num - n),
-            "mul" | "*" => result.push(num * n),
-            "div" | "/" => result.push(num / n),
-            _ => eprintln!("Invalid operation"),
+    for (index, &num) in array.iter().enumerate() {
+        match apply(num, n) {
+            Some(value) => result.push(value),
+            None => return Err(MapError::ElementOverflow { index, value: num }),
+        }
+    }
+    Ok(result)
+}
+
+fn map_float_array(array: Vec<f64>, op: &str, n: f64) -> Result<Vec<f64>, MapError> {
+    let apply: fn(f64, f64) -> f64 = match op {
+        "add" | "+" => |a, b| a + b,
+        "sub" | "-" => |a, b| a - b,
+        "mul" | "*" => |a, b| a * b,
+        "div" | "/" if n == 0.0 => return Err(MapError::DivisionByZero),
+        "div" | "/" => |a, b| a / b,
+        _ => return Err(MapError::InvalidOperation(op.to_string())),
+    };
+    let mut result: Vec<f64> = Vec::new();
+    for (index, &num) in array.iter().enumerate() {
+        match apply(num, n) {
+            value if value.is_finite() => result.push(value),
+            _ => return Err(MapError::FloatOverflow { index, value: num }),
+        }
+    }
+    Ok(result)
+}
+
+fn parse_numbers<T: FromStr>(tokens: &[&str]) -> Result<Vec<T>, MapError> {
+    tokens
+        .iter()
+        .map(|token| {
+            token
+                .parse()
+                .map_err(|_| MapError::InvalidNumber(token.to_string()))
+        })
+        .collect()
+}
+
+// Works on f64 as soon as the array or the operand has a decimal point, so
+// that division doesn't truncate, and on isize otherwise.
+fn map_input(tokens: &[&str], op: &str, operand: &str) -> Result<String, MapError> {
+    if tokens
+        .iter()
+        .chain([&operand])
+        .any(|token| token.contains('.'))
+    {
+        let n = parse_numbers::<f64>(&[operand])?[0];
+        let result = map_float_array(parse_numbers(tokens)?, op, n)?;
+        Ok(format!("{:?}", result))
+    } else {
+        let n = parse_numbers::<isize>(&[operand])?[0];
+        let result = map_array(parse_numbers(tokens)?, op, n)?;
+        Ok(format!("{:?}", result))
+    }
+}
+
+fn is_reduction(op: &str) -> bool {
+    matches!(op, "sum" | "product" | "min" | "max")
+}
+
+fn reduce_array(array: &[isize], op: &str) -> Result<isize, MapError> {
+    match op {
+        "sum" => array
+            .iter()
+            .try_fold(0isize, |acc, &num| acc.checked_add(num))
+            .ok_or(MapError::Overflow(op.to_string())),
+        "product" => array
+            .iter()
+            .try_fold(1isize, |acc, &num| acc.checked_mul(num))
+            .ok_or(MapError::Overflow(op.to_string())),
+        "min" => array
+            .iter()
+            .copied()
+            .min()
+            .ok_or(MapError::EmptyArray(op.to_string())),
+        "max" => array
+            .iter()
+            .copied()
+            .max()
+            .ok_or(MapError::EmptyArray(op.to_string())),
+        _ => Err(MapError::InvalidOperation(op.to_string())),
+    }
+}
+
+// Counts how many values fall in each bucket, as (lowest, highest, count).
+// Empty buckets are left out.
+fn histogram(values: &[isize], bucket: usize) -> Result<Vec<(isize, isize, usize)>, MapError> {
+    if bucket == 0 {
+        return Err(MapError::ZeroBucketSize);
+    }
+    let bucket = isize::try_from(bucket).map_err(|_| MapError::BucketTooLarge(bucket))?;
+    let mut counts: BTreeMap<isize, usize> = BTreeMap::new();
+    for &value in values {
+        *counts.entry(value.div_euclid(bucket)).or_insert(0) += 1;
+    }
+    // The outermost buckets can reach past isize, so their bounds are
+    // computed unclamped and then clamped to the values they can hold.
+    let clamp = |bound: i128| bound.clamp(isize::MIN as i128, isize::MAX as i128) as isize;
+    Ok(counts
+        .into_iter()
+        .map(|(index, count)| {
+            let start = index as i128 * bucket as i128;
+            (clamp(start), clamp(start + bucket as i128 - 1), count)
+        })
+        .collect())
+}
+
+fn print_histogram(values: &[isize], bucket: &str) {
+    let bucket: usize = match bucket.trim().parse() {
+        Ok(size) => size,
+        Err(_) => {
+            eprintln!("Invalid bucket size");
+            return;
+        }
+    };
+    match histogram(values, bucket) {
+        Ok(counts) => {
+            for (start, end, count) in counts {
+                println!("{}-{}: {}", start, end, "#".repeat(count));
+            }
         }
+        Err(e) => eprintln!("Error: {}", e),
     }
-    result
 }
 
 fn main() {
@@ -20,39 +186,140 @@ fn main() {
         let mut input = String::new();
         print!("Enter array numbers (space separated): ");
         stdout().flush().unwrap();
-        stdin().read_line(&mut input).expect("Failed to read line");
-        let mut numbers: Vec<isize> = Vec::new();
-        for num in input.trim().split_whitespace() {
-            numbers.push(num.parse().unwrap());
+        if stdin().read_line(&mut input).expect("Failed to read line") == 0 {
+            break;
         }
+        let tokens: Vec<&str> = input.split_whitespace().collect();
 
-        print!("Enter basic operation (either by first three letters or by symbol): ");
+        print!("Enter basic operation (either by first three letters or by symbol), sum, product, min, max or histogram <bucket_size>: ");
         stdout().flush().unwrap();
-        input = String::new();
-        stdin().read_line(&mut input).expect("Failed to read line");
-        let op = input.trim().to_lowercase();
+        let mut op = String::new();
+        stdin().read_line(&mut op).expect("Failed to read line");
+        let op = op.trim().to_lowercase();
 
-        print!("Enter number: ");
-        stdout().flush().unwrap();
-        input = String::new();
-        stdin().read_line(&mut input).expect("Failed to read line");
-        let n: isize = match input.trim().parse() {
-            Ok(num) => num,
-            Err(_) => {
-                println!("Invalid input");
-                return;
+        if let Some(bucket) = op.strip_prefix("histogram") {
+            match parse_numbers::<isize>(&tokens) {
+                Ok(numbers) => print_histogram(&numbers, bucket),
+                Err(e) => eprintln!("Error: {}", e),
             }
-        };
-
-        let result: Vec<isize> = map_array(numbers, &op.trim(), n);
-        print!("Result: [");
-        for (i, &num) in result.iter().enumerate() {
-            if i != result.len() - 1 {
-                print!("{}, ", num);
-            } else {
-                println!("{}]", num);
+            continue;
+        }
+
+        if is_reduction(&op) {
+            match parse_numbers::<isize>(&tokens).and_then(|numbers| reduce_array(&numbers, &op)) {
+                Ok(result) => println!("Result: {}", result),
+                Err(e) => eprintln!("Error: {}", e),
             }
+            continue;
         }
+
+        print!("Enter number: ");
         stdout().flush().unwrap();
+        let mut operand = String::new();
+        stdin()
+            .read_line(&mut operand)
+            .expect("Failed to read line");
+
+        match map_input(&tokens, &op, operand.trim()) {
+            Ok(result) => println!("Result: {}", result),
+            Err(e) => eprintln!("Error: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reduce_array() {
+        assert_eq!(reduce_array(&[3, 1, 2], "sum"), Ok(6));
+        assert_eq!(reduce_array(&[3, 1, 2], "max"), Ok(3));
+        assert_eq!(reduce_array(&[3, 1, 2], "min"), Ok(1));
+        assert_eq!(reduce_array(&[3, 1, 2], "product"), Ok(6));
+        assert_eq!(reduce_array(&[], "sum"), Ok(0));
+        assert_eq!(
+            reduce_array(&[], "min"),
+            Err(MapError::EmptyArray("min".to_string()))
+        );
+        assert_eq!(
+            reduce_array(&[isize::MAX, 2], "product"),
+            Err(MapError::Overflow("product".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_histogram() {
+        assert_eq!(
+            histogram(&[1, 2, 3, 11, 12], 10),
+            Ok(vec![(0, 9, 3), (10, 19, 2)])
+        );
+        assert_eq!(
+            histogram(&[-1, -10, 5], 10),
+            Ok(vec![(-10, -1, 2), (0, 9, 1)])
+        );
+        assert_eq!(histogram(&[], 10), Ok(vec![]));
+        assert_eq!(histogram(&[1], 0), Err(MapError::ZeroBucketSize));
+        assert_eq!(
+            histogram(&[1], usize::MAX),
+            Err(MapError::BucketTooLarge(usize::MAX))
+        );
+        assert_eq!(
+            histogram(&[isize::MAX, isize::MIN], 10),
+            Ok(vec![
+                (isize::MIN, isize::MIN + 7, 1),
+                (isize::MAX - 7, isize::MAX, 1)
+            ])
+        );
+    }
+
+    #[test]
+    fn test_map_array_checks_overflow() {
+        assert_eq!(map_array(vec![1, 2, 3], "mul", 2), Ok(vec![2, 4, 6]));
+        assert_eq!(map_array(vec![7, -7], "/", 2), Ok(vec![3, -3]));
+        assert_eq!(
+            map_array(vec![1, isize::MAX, 3], "+", 1),
+            Err(MapError::ElementOverflow {
+                index: 1,
+                value: isize::MAX
+            })
+        );
+        assert_eq!(
+            map_array(vec![isize::MIN], "div", -1),
+            Err(MapError::ElementOverflow {
+                index: 0,
+                value: isize::MIN
+            })
+        );
+        assert_eq!(map_array(vec![1], "div", 0), Err(MapError::DivisionByZero));
+        assert_eq!(
+            map_array(vec![1], "pow", 2),
+            Err(MapError::InvalidOperation("pow".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_map_input_switches_to_floats() {
+        assert_eq!(map_input(&["7", "9"], "div", "2"), Ok("[3, 4]".to_string()));
+        assert_eq!(
+            map_input(&["7", "9"], "div", "2.0"),
+            Ok("[3.5, 4.5]".to_string())
+        );
+        assert_eq!(
+            map_input(&["1.5", "2"], "+", "1"),
+            Ok("[2.5, 3.0]".to_string())
+        );
+        assert_eq!(map_input(&["1.5"], "/", "0"), Err(MapError::DivisionByZero));
+        assert_eq!(
+            map_input(&["1e308"], "*", "10.0"),
+            Err(MapError::FloatOverflow {
+                index: 0,
+                value: 1e308
+            })
+        );
+        assert_eq!(
+            map_input(&["1", "x"], "+", "1"),
+            Err(MapError::InvalidNumber("x".to_string()))
+        );
     }
 }
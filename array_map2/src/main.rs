@@ -7,6 +7,41 @@ where
     array.into_iter().map(op).collect()
 }
 
+// Composes op with itself, so repeat(op, 3)(x) is op(op(op(x))).
+fn repeat<F>(op: F, times: usize) -> impl Fn(isize) -> isize
+where
+    F: Fn(isize) -> isize,
+{
+    move |x| (0..times).fold(x, |acc, _| op(acc))
+}
+
+type Operation = Box<dyn Fn(isize) -> isize>;
+
+// Builds the closure for op and n, the same one the prompt would apply.
+fn operation(op: &str, n: isize) -> Result<Operation, String> {
+    match op {
+        "add" | "+" => Ok(Box::new(move |x| x + n)),
+        "sub" | "-" => Ok(Box::new(move |x| x - n)),
+        "mul" | "*" => Ok(Box::new(move |x| x * n)),
+        "div" | "/" | "mod" | "%" if n == 0 => Err("Cannot divide by zero".to_string()),
+        "div" | "/" => Ok(Box::new(move |x| x / n)),
+        // Only isize::MIN % -1 can fail, and its remainder is 0.
+        "mod" | "%" => Ok(Box::new(move |x| x.checked_rem(n).unwrap_or(0))),
+        "pow" | "^" => match u32::try_from(n) {
+            // Powers too large for an isize saturate.
+            Ok(exp) => Ok(Box::new(move |x: isize| match x.checked_pow(exp) {
+                Some(power) => power,
+                None if x < 0 && exp % 2 == 1 => isize::MIN,
+                None => isize::MAX,
+            })),
+            Err(_) => Err("The exponent must be a non-negative number".to_string()),
+        },
+        "min" => Ok(Box::new(move |x| x.min(n))),
+        "max" => Ok(Box::new(move |x| x.max(n))),
+        _ => Err("Invalid operation".to_string()),
+    }
+}
+
 fn main() {
     loop {
         let mut input = String::new();
@@ -18,7 +53,7 @@ fn main() {
             .map(|num| num.parse().expect("Invalid number"))
             .collect();
 
-        print!("Enter basic operation (either by first three letters or by symbol): ");
+        print!("Enter basic operation (either by first three letters or by symbol), min or max: ");
         io::stdout().flush().unwrap();
         input = String::new();
         io::stdin().read_line(&mut input).expect("Failed to read line");
@@ -30,17 +65,64 @@ fn main() {
         io::stdin().read_line(&mut input).expect("Failed to read line");
         let n: isize = input.trim().parse().expect("Invalid input");
 
-        let result: Vec<isize> = match op.as_str() {
-            "add" | "+" => map_array(numbers, |x| x + n),
-            "sub" | "-" => map_array(numbers, |x| x - n),
-            "mul" | "*" => map_array(numbers, |x| x * n),
-            "div" | "/" => map_array(numbers, |x| x / n),
-            _ => {
-                eprintln!("Invalid operation");
+        let op = match operation(&op, n) {
+            Ok(op) => op,
+            Err(e) => {
+                eprintln!("{}", e);
                 continue;
             }
         };
 
+        print!("Enter how many times to apply it (default 1): ");
+        io::stdout().flush().unwrap();
+        input = String::new();
+        io::stdin().read_line(&mut input).expect("Failed to read line");
+        let times: usize = match input.trim() {
+            "" => 1,
+            times => times.parse().expect("Invalid input"),
+        };
+
+        let result = map_array(numbers, repeat(op, times));
+
         println!("Result: {:?}", result);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeat() {
+        assert_eq!(map_array(vec![1], repeat(|x| x * 2, 3)), vec![8]);
+        assert_eq!(map_array(vec![1, 2], repeat(|x| x + 1, 3)), vec![4, 5]);
+        assert_eq!(map_array(vec![1, -2], repeat(|x| x * 2, 0)), vec![1, -2]);
+    }
+
+    fn apply(op: &str, n: isize, array: Vec<isize>) -> Vec<isize> {
+        map_array(array, operation(op, n).unwrap())
+    }
+
+    #[test]
+    fn test_operations() {
+        assert_eq!(apply("pow", 3, vec![2, -2, 0]), vec![8, -8, 0]);
+        assert_eq!(apply("^", 0, vec![5]), vec![1]);
+        assert_eq!(
+            apply("^", 64, vec![2, -3, 1]),
+            vec![isize::MAX, isize::MAX, 1]
+        );
+        assert_eq!(apply("pow", 63, vec![-3]), vec![isize::MIN]);
+        assert_eq!(apply("mod", 3, vec![7, -7]), vec![1, -1]);
+        assert_eq!(apply("%", -1, vec![isize::MIN]), vec![0]);
+        assert_eq!(apply("min", 2, vec![1, 2, 3]), vec![1, 2, 2]);
+        assert_eq!(apply("max", 2, vec![1, 2, 3]), vec![2, 2, 3]);
+        assert_eq!(
+            map_array(vec![2], repeat(operation("^", 2).unwrap(), 2)),
+            vec![16]
+        );
+        assert!(operation("%", 0).is_err());
+        assert!(operation("/", 0).is_err());
+        assert!(operation("pow", -1).is_err());
+        assert!(operation("log", 2).is_err());
+    }
+}
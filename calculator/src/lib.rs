@@ -0,0 +1,663 @@
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    error::Error,
+    fmt,
+    iter::{Enumerate, Peekable},
+    slice::Iter,
+    str::Chars,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+enum Token {
+    Plus,
+    Dash,
+    Star,
+    Slash,
+    Caret,
+    Percent,
+    LeftParen,
+    RightParen,
+    Equals,
+    EqualEqual,
+    BangEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    Let,
+    End,
+    Number(f64),
+    Identifier(String),
+}
+
+impl Token {
+    fn is_binary(&self) -> bool {
+        matches!(
+            self,
+            Token::Plus
+                | Token::Dash
+                | Token::Star
+                | Token::Slash
+                | Token::Caret
+                | Token::Percent
+                | Token::EqualEqual
+                | Token::BangEqual
+                | Token::Greater
+                | Token::GreaterEqual
+                | Token::Less
+                | Token::LessEqual
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Operator {
+    Add,
+    Multiply,
+    Divide,
+    Modulo,
+    Subtract,
+    Power,
+    Negative,
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+}
+
+impl Operator {
+    // Binding strength, higher binds tighter: comparisons (0) < + - (1)
+    // < unary minus (2) < * / % (3) < ^ (4). So `2 > 1 + 1` is `2 > (1 + 1)`.
+    fn cmp_val(&self) -> usize {
+        match self {
+            Operator::Equal
+            | Operator::NotEqual
+            | Operator::Greater
+            | Operator::GreaterEqual
+            | Operator::Less
+            | Operator::LessEqual => 0,
+            Operator::Add => 1,
+            Operator::Multiply => 3,
+            Operator::Divide => 3,
+            Operator::Modulo => 3,
+            Operator::Subtract => 1,
+            Operator::Power => 4,
+            Operator::Negative => 2,
+        }
+    }
+}
+
+impl TryFrom<Token> for Operator {
+    type Error = &'static str;
+
+    fn try_from(token: Token) -> Result<Self, Self::Error> {
+        match token {
+            Token::Plus => Ok(Operator::Add),
+            Token::Star => Ok(Operator::Multiply),
+            Token::Dash => Ok(Operator::Subtract),
+            Token::Caret => Ok(Operator::Power),
+            Token::Slash => Ok(Operator::Divide),
+            Token::Percent => Ok(Operator::Modulo),
+            Token::EqualEqual => Ok(Operator::Equal),
+            Token::BangEqual => Ok(Operator::NotEqual),
+            Token::Greater => Ok(Operator::Greater),
+            Token::GreaterEqual => Ok(Operator::GreaterEqual),
+            Token::Less => Ok(Operator::Less),
+            Token::LessEqual => Ok(Operator::LessEqual),
+            _ => Err("Token is not an operator"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Expression {
+    Number(f64),
+    Variable(String),
+    Unary(Operator, Box<Expression>),
+    Binary(Operator, Box<Expression>, Box<Expression>),
+}
+
+impl Expression {
+    fn eval(&self, variables: &HashMap<String, f64>) -> Result<f64, CalcError> {
+        match self {
+            Expression::Number(n) => Ok(*n),
+            Expression::Variable(name) => match variables.get(name) {
+                Some(value) => Ok(*value),
+                None => Err(CalcError::UndefinedVariable(name.clone())),
+            },
+            Expression::Unary(_negative, expr) => Ok(-expr.eval(variables)?),
+            Expression::Binary(Operator::Add, expr1, expr2) => {
+                Ok(expr1.eval(variables)? + expr2.eval(variables)?)
+            }
+            Expression::Binary(Operator::Multiply, expr1, expr2) => {
+                Ok(expr1.eval(variables)? * expr2.eval(variables)?)
+            }
+            Expression::Binary(Operator::Subtract, expr1, expr2) => {
+                Ok(expr1.eval(variables)? - expr2.eval(variables)?)
+            }
+            Expression::Binary(Operator::Power, expr1, expr2) => {
+                let base = expr1.eval(variables)?;
+                let exponent = expr2.eval(variables)?;
+                let operation = format!("{} ^ {}", base, exponent);
+                if base == 0.0 && exponent < 0.0 {
+                    return Err(CalcError::DivisionByZero);
+                }
+                // Whole numbers with a non-negative exponent stay exact while
+                // they fit in an i64, past that f64 takes over
+                if base.fract() == 0.0
+                    && base.abs() < 2f64.powi(63)
+                    && exponent.fract() == 0.0
+                    && (0.0..=u32::MAX as f64).contains(&exponent)
+                {
+                    if let Some(v) = (base as i64).checked_pow(exponent as u32) {
+                        return Ok(v as f64);
+                    }
+                }
+
+                let result = base.powf(exponent);
+                if result.is_nan() {
+                    Err(CalcError::NotARealNumber(operation))
+                } else if result.is_infinite() {
+                    Err(CalcError::Overflow(operation))
+                } else {
+                    Ok(result)
+                }
+            }
+            Expression::Binary(Operator::Divide, expr1, expr2) => {
+                let divisor = expr2.eval(variables)?;
+                if divisor == 0.0 {
+                    return Err(CalcError::DivisionByZero);
+                }
+                Ok(expr1.eval(variables)? / divisor)
+            }
+            Expression::Binary(Operator::Modulo, expr1, expr2) => {
+                let divisor = expr2.eval(variables)?;
+                if divisor == 0.0 {
+                    return Err(CalcError::DivisionByZero);
+                }
+                Ok(expr1.eval(variables)? % divisor)
+            }
+            Expression::Binary(operator, expr1, expr2) if operator.cmp_val() == 0 => {
+                let lhs = expr1.eval(variables)?;
+                let rhs = expr2.eval(variables)?;
+                let result = match operator {
+                    Operator::Equal => lhs == rhs,
+                    Operator::NotEqual => lhs != rhs,
+                    Operator::Greater => lhs > rhs,
+                    Operator::GreaterEqual => lhs >= rhs,
+                    Operator::Less => lhs < rhs,
+                    _ => lhs <= rhs,
+                };
+                Ok(if result { 1.0 } else { 0.0 })
+            }
+            _ => {
+                panic!("Unreachable code: for expr {:?}", self);
+            }
+        }
+    }
+}
+
+
+#[derive(Debug, PartialEq)]
+pub enum CalcError {
+    Lex(String),
+    Parse(String),
+    Overflow(String),
+    DivisionByZero,
+    UndefinedVariable(String),
+    NotARealNumber(String),
+}
+
+impl fmt::Display for CalcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CalcError::Lex(message) => write!(f, "Lex Error {}:", message),
+            CalcError::Parse(message) => write!(f, "Parse Error {}:", message),
+            CalcError::Overflow(operation) => write!(f, "Eval Error {} is too large:", operation),
+            CalcError::DivisionByZero => write!(f, "Eval Error Division by zero:"),
+            CalcError::UndefinedVariable(name) => {
+                write!(f, "Eval Error Undefined variable {}:", name)
+            }
+            CalcError::NotARealNumber(operation) => {
+                write!(f, "Eval Error {} is not a real number:", operation)
+            }
+        }
+    }
+}
+
+impl Error for CalcError {}
+
+
+#[derive(Debug, PartialEq)]
+enum Statement {
+    Let(String, Expression),
+    Expression(Expression),
+}
+
+
+struct Parser<'a> {
+    iter: &'a mut Peekable<Iter<'a, Token>>,
+    // Number of '(' waiting for their ')'
+    open_parens: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(iter: &'a mut Peekable<Iter<'a, Token>>) -> Self {
+        Parser {
+            iter,
+            open_parens: 0,
+        }
+    }
+
+    fn assert_next(&mut self, token: Token) -> Result<(), CalcError> {
+        match (self.iter.next(), &token) {
+            (Some(next), _) if *next == token => Ok(()),
+            (None | Some(Token::End), Token::RightParen) => Err(CalcError::Parse(format!(
+                "Missing ')', {} parenthesis still open at end of input",
+                self.open_parens
+            ))),
+            (Some(Token::RightParen), Token::End) => Err(CalcError::Parse(
+                "Found ')' without a matching '('".to_string(),
+            )),
+            (None, _) => Err(CalcError::Parse("Unexpected end of input".to_string())),
+            (Some(next), _) => Err(CalcError::Parse(format!(
+                "Expected {:?}, found {:?}",
+                token, next
+            ))),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expression, CalcError> {
+        match self.iter.next() {
+            Some(Token::Dash) => {
+                let op = Operator::Negative;
+                let expr = self.expression(op.cmp_val())?;
+                Ok(Expression::Unary(op, Box::new(expr)))
+            }
+            Some(Token::LeftParen) => {
+                self.open_parens += 1;
+                let expr = self.expression(0)?;
+                self.assert_next(Token::RightParen)?;
+                self.open_parens -= 1;
+                Ok(expr)
+            }
+            Some(Token::Number(n)) => Ok(Expression::Number(*n)),
+            Some(Token::Identifier(name)) => Ok(Expression::Variable(name.clone())),
+            None | Some(Token::End) => Err(CalcError::Parse(
+                "Expected a number or '(' but reached end of input".to_string(),
+            )),
+            Some(Token::RightParen) if self.open_parens == 0 => Err(CalcError::Parse(
+                "Found ')' without a matching '('".to_string(),
+            )),
+            Some(tok) => Err(CalcError::Parse(format!(
+                "Unexpected token {:?}",
+                tok
+            ))),
+        }
+    }
+
+
+    fn expression(&mut self, precedence: usize) -> Result<Expression, CalcError> {
+        let mut expr = self.primary()?;
+        while let Some(tok) = self.iter.peek() {
+            if !tok.is_binary() {
+                break;
+            }
+            let operator = Operator::try_from((*tok).clone()).unwrap();
+            if operator.cmp_val() < precedence {
+                break;
+            }
+            self.iter.next();
+            let inner_precedence = match operator {
+                Operator:: Power => operator.cmp_val(),
+                _ => 1+ operator.cmp_val(),
+            };
+            let rhs = self.expression(inner_precedence)?;
+            expr = Expression::Binary(operator, Box::new(expr), Box::new(rhs));
+        }
+
+        Ok(expr)
+    }
+
+
+    // let <name> = <expression>
+    fn assignment(&mut self) -> Result<Statement, CalcError> {
+        self.assert_next(Token::Let)?;
+        let name = match self.iter.next() {
+            Some(Token::Identifier(name)) => name.clone(),
+            tok => {
+                return Err(CalcError::Parse(format!(
+                    "Expected a variable name, found {:?}",
+                    tok
+                )));
+            }
+        };
+        self.assert_next(Token::Equals)?;
+        let expr = self.expression(0)?;
+        Ok(Statement::Let(name, expr))
+    }
+
+
+    fn parse(&mut self) -> Result<Statement, CalcError> {
+        let ast = match self.iter.peek() {
+            Some(Token::Let) => self.assignment()?,
+            _ => Statement::Expression(self.expression(0)?),
+        };
+        self.assert_next(Token::End)?;
+        Ok(ast)
+    }
+
+
+}
+
+
+// Reads the rest of a number whose first digit is `first`, e.g. "12", "2.5",
+// "1e3" or "2.5E-2". An exponent marker has to be followed by at least one
+// digit, optionally after a sign.
+fn lex_number(
+    first: char,
+    start: usize,
+    iter: &mut Peekable<Enumerate<Chars>>,
+) -> Result<f64, CalcError> {
+    let mut number = String::from(first);
+    let mut seen_dot = false;
+    while let Some(&(_, c)) = iter.peek() {
+        if c.is_ascii_digit() || (c == '.' && !seen_dot) {
+            seen_dot |= c == '.';
+            number.push(c);
+            iter.next();
+        } else {
+            break;
+        }
+    }
+
+    if let Some(&(position, marker @ ('e' | 'E'))) = iter.peek() {
+        number.push(marker);
+        iter.next();
+        if let Some(&(_, sign @ ('+' | '-'))) = iter.peek() {
+            number.push(sign);
+            iter.next();
+        }
+
+        let mut digits = 0;
+        while let Some(&(_, c)) = iter.peek() {
+            if !c.is_ascii_digit() {
+                break;
+            }
+            number.push(c);
+            iter.next();
+            digits += 1;
+        }
+        if digits == 0 {
+            return Err(CalcError::Lex(format!(
+                "Missing exponent digits in {} at position {}",
+                number,
+                position + 1
+            )));
+        }
+    }
+
+    number.parse().map_err(|_| {
+        CalcError::Lex(format!(
+            "Invalid number {} at position {}",
+            number,
+            start + 1
+        ))
+    })
+}
+
+
+fn lex(code: String) -> Result<Vec<Token>, CalcError> {
+    let mut iter = code.chars().enumerate().peekable();
+    let mut tokens: Vec<Token> = Vec::new();
+
+    while let Some((position, ch)) = iter.next() {
+        match ch {
+            ' ' => continue,
+            '+' => tokens.push(Token::Plus),
+            '-' => tokens.push(Token::Dash),
+            '*' => tokens.push(Token::Star),
+            '/' => tokens.push(Token::Slash),
+            '^' => tokens.push(Token::Caret),
+            '%' => tokens.push(Token::Percent),
+            ')' => tokens.push(Token::RightParen),
+            '(' => tokens.push(Token::LeftParen),
+            '=' | '!' | '<' | '>' => {
+                let followed_by_equals = iter.next_if(|&(_, c)| c == '=').is_some();
+                let token = match (ch, followed_by_equals) {
+                    ('=', true) => Token::EqualEqual,
+                    ('=', false) => Token::Equals,
+                    ('!', true) => Token::BangEqual,
+                    ('<', true) => Token::LessEqual,
+                    ('<', false) => Token::Less,
+                    ('>', true) => Token::GreaterEqual,
+                    ('>', false) => Token::Greater,
+                    _ => {
+                        return Err(CalcError::Lex(format!(
+                            "Unexpected character {}",
+                            ch
+                        )));
+                    }
+                };
+                tokens.push(token);
+            }
+            ch if ch.is_ascii_digit() => {
+                let number = lex_number(ch, position, &mut iter)?;
+                tokens.push(Token::Number(number));
+            }
+            ch if ch.is_alphabetic() || ch == '_' => {
+                let mut name = String::from(ch);
+                while let Some(&(_, c)) = iter.peek() {
+                    if !(c.is_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    name.push(c);
+                    iter.next();
+                }
+                match name.as_str() {
+                    "let" => tokens.push(Token::Let),
+                    _ => tokens.push(Token::Identifier(name)),
+                }
+            }
+
+            _ => {
+                return Err(CalcError::Lex(format!(
+                    "Unexpected character {}",
+                    ch
+                )));
+            }
+        }
+    }
+
+    tokens.push(Token::End);
+
+    Ok(tokens)
+}
+
+
+// Runs the whole lex, parse and eval pipeline on a single line of input.
+pub fn evaluate(input: &str) -> Result<f64, CalcError> {
+    evaluate_with(input, &mut HashMap::new())
+}
+
+// Like evaluate, but reads and assigns variables in `variables`, so `let`
+// bindings persist across calls.
+pub fn evaluate_with(
+    input: &str,
+    variables: &mut HashMap<String, f64>,
+) -> Result<f64, CalcError> {
+    let tokens = lex(input.to_string())?;
+    let mut token_iter = tokens.iter().peekable();
+    let mut parser = Parser::new(&mut token_iter);
+    match parser.parse()? {
+        Statement::Let(name, expr) => {
+            let value = expr.eval(variables)?;
+            variables.insert(name, value);
+            Ok(value)
+        }
+        Statement::Expression(expr) => expr.eval(variables),
+    }
+}
+
+// One `name = value` line per variable, sorted by name.
+pub fn list_variables(variables: &HashMap<String, f64>) -> Vec<String> {
+    let mut names: Vec<&String> = variables.keys().collect();
+    names.sort();
+    names
+        .into_iter()
+        .map(|name| format!("{} = {}", name, variables[name]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_errors_are_typed() {
+        assert!(matches!(evaluate("2 $ 3"), Err(CalcError::Lex(_))));
+        assert!(matches!(evaluate("2 3"), Err(CalcError::Parse(_))));
+        assert_eq!(evaluate("2 ^ 100"), Ok(2f64.powi(100)));
+        assert_eq!(evaluate("2 + 3 * 4"), Ok(14.0));
+    }
+
+    #[test]
+    fn test_modulo() {
+        assert_eq!(evaluate("17 % 5"), Ok(2.0));
+        assert_eq!(evaluate("2 + 17 % 5 * 3"), Ok(8.0));
+        assert_eq!(evaluate("-7 % 3"), Ok(-1.0));
+        assert_eq!(evaluate("5 % 0"), Err(CalcError::DivisionByZero));
+    }
+
+    fn parse(code: &str) -> Result<Statement, CalcError> {
+        let tokens = lex(code.to_string())?;
+        let mut token_iter = tokens.iter().peekable();
+        let mut parser = Parser::new(&mut token_iter);
+        parser.parse()
+    }
+
+    #[test]
+    fn test_unary_minus() {
+        assert_eq!(evaluate("--3"), Ok(3.0));
+        assert_eq!(evaluate("3 - -2"), Ok(5.0));
+        assert_eq!(evaluate("-(3)"), Ok(-3.0));
+        assert_eq!(evaluate("-(2+3)"), Ok(-5.0));
+        assert_eq!(
+            parse("2 ^ -3"),
+            Ok(Statement::Expression(Expression::Binary(
+                Operator::Power,
+                Box::new(Expression::Number(2.0)),
+                Box::new(Expression::Unary(Operator::Negative, Box::new(Expression::Number(3.0)))),
+            )))
+        );
+    }
+
+    #[test]
+    fn test_scientific_notation() {
+        assert_eq!(evaluate("1e3"), Ok(1000.0));
+        assert_eq!(evaluate("2.5e-1"), Ok(0.25));
+        assert_eq!(evaluate("2.5E-2 * 4"), Ok(0.1));
+        assert_eq!(evaluate("1e+6"), Ok(1000000.0));
+        assert_eq!(evaluate("1.5 + 1"), Ok(2.5));
+        assert!(matches!(evaluate("1e"), Err(CalcError::Lex(_))));
+        assert!(matches!(evaluate("1e+"), Err(CalcError::Lex(_))));
+    }
+
+    #[test]
+    fn test_division_by_zero() {
+        assert_eq!(evaluate("4 / 0"), Err(CalcError::DivisionByZero));
+        assert_eq!(evaluate("4 / (2 - 2)"), Err(CalcError::DivisionByZero));
+        assert_eq!(evaluate("0 / 4"), Ok(0.0));
+    }
+
+    #[test]
+    fn test_let_bindings() {
+        let mut variables = HashMap::new();
+        assert_eq!(evaluate_with("let x = 3 + 4", &mut variables), Ok(7.0));
+        assert_eq!(evaluate_with("let y_2 = x * 2", &mut variables), Ok(14.0));
+        assert_eq!(evaluate_with("x + y_2", &mut variables), Ok(21.0));
+        assert_eq!(evaluate_with("let x = x - 1", &mut variables), Ok(6.0));
+        assert_eq!(variables.get("x"), Some(&6.0));
+        assert_eq!(
+            evaluate_with("z + 1", &mut variables),
+            Err(CalcError::UndefinedVariable("z".to_string()))
+        );
+        assert!(matches!(evaluate_with("let = 3", &mut variables), Err(CalcError::Parse(_))));
+        assert!(matches!(evaluate_with("let 3 = 3", &mut variables), Err(CalcError::Parse(_))));
+    }
+
+    #[test]
+    fn test_power() {
+        assert_eq!(evaluate("2 ^ -1"), Ok(0.5));
+        assert_eq!(evaluate("2 ^ -3"), Ok(0.125));
+        assert_eq!(evaluate("5 ^ 0"), Ok(1.0));
+        assert_eq!(evaluate("0 ^ 0"), Ok(1.0));
+        assert_eq!(evaluate("3 ^ 2 ^ 2"), Ok(81.0));
+        assert_eq!(evaluate("2 ^ 0.5"), Ok(2_f64.sqrt()));
+        assert_eq!(evaluate("4 ^ 0.5"), Ok(2.0));
+        assert_eq!(evaluate("3 ^ 39"), Ok(4052555153018976267.0));
+        assert_eq!(evaluate("2 ^ 64"), Ok(2f64.powi(64)));
+        assert_eq!(evaluate("10 ^ 19"), Ok(1e19));
+        assert_eq!(evaluate("9223372036854775808 ^ 1"), Ok(2f64.powi(63)));
+        assert_eq!(evaluate("0 ^ -1"), Err(CalcError::DivisionByZero));
+        assert!(matches!(evaluate("10 ^ 400.5"), Err(CalcError::Overflow(_))));
+        assert!(matches!(evaluate("(0 - 8) ^ 0.5"), Err(CalcError::NotARealNumber(_))));
+    }
+
+    #[test]
+    fn test_malformed_input_diagnostics() {
+        let end_of_input = "Expected a number or '(' but reached end of input";
+        let unmatched = "Found ')' without a matching '('";
+        assert_eq!(evaluate("2 +"), Err(CalcError::Parse(end_of_input.to_string())));
+        assert_eq!(evaluate(""), Err(CalcError::Parse(end_of_input.to_string())));
+        assert_eq!(
+            evaluate("(1+2"),
+            Err(CalcError::Parse(
+                "Missing ')', 1 parenthesis still open at end of input".to_string()
+            ))
+        );
+        assert_eq!(
+            evaluate("((1+2) * (3"),
+            Err(CalcError::Parse(
+                "Missing ')', 2 parenthesis still open at end of input".to_string()
+            ))
+        );
+        assert_eq!(evaluate(")"), Err(CalcError::Parse(unmatched.to_string())));
+        assert_eq!(evaluate("1 + 2)"), Err(CalcError::Parse(unmatched.to_string())));
+        assert!(matches!(evaluate("(2 +)"), Err(CalcError::Parse(_))));
+    }
+
+    #[test]
+    fn test_comparisons() {
+        assert_eq!(evaluate("3 > 2"), Ok(1.0));
+        assert_eq!(evaluate("3 < 2"), Ok(0.0));
+        assert_eq!(evaluate("1 + 1 == 2"), Ok(1.0));
+        assert_eq!(evaluate("2 > 1 + 1"), Ok(0.0));
+        assert_eq!(evaluate("2 >= 1 + 1"), Ok(1.0));
+        assert_eq!(evaluate("2 * 3 <= 5"), Ok(0.0));
+        assert_eq!(evaluate("4 != 2 ^ 2"), Ok(0.0));
+        assert_eq!(evaluate("-1 < 0"), Ok(1.0));
+        assert_eq!(evaluate("(1 < 2) + (3 == 3)"), Ok(2.0));
+        assert!(matches!(evaluate("1 ! 2"), Err(CalcError::Lex(_))));
+    }
+
+    #[test]
+    fn test_list_and_clear_variables() {
+        let mut variables = HashMap::new();
+        assert!(list_variables(&variables).is_empty());
+
+        assert_eq!(evaluate_with("let y = 2", &mut variables), Ok(2.0));
+        assert_eq!(evaluate_with("let x = y * 1.5", &mut variables), Ok(3.0));
+        assert_eq!(list_variables(&variables), vec!["x = 3", "y = 2"]);
+
+        variables.clear();
+        assert!(list_variables(&variables).is_empty());
+        assert!(matches!(
+            evaluate_with("x", &mut variables),
+            Err(CalcError::UndefinedVariable(_))
+        ));
+    }
+}
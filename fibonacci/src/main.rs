@@ -1,41 +1,232 @@
-use rug::Integer;
-use std::{io, io::prelude::*, time::Instant};
+use rug::{Float, Integer};
+use std::{
+    collections::HashMap,
+    io,
+    io::prelude::*,
+    time::{Duration, Instant},
+};
 
-fn main() {
-    fn calculate_fibonacci(n: u32) -> String {
-        let mut table: Vec<Integer> = Vec::new();
-        if table.len() == 0 {
-            table.push(Integer::from(0));
-            table.push(Integer::from(1));
-        }
-        while table.len() <= n as usize {
-            let len = table.len();
-            let next = &table[len - 1] + &table[len - 2];
-            table.push(Integer::from(next));
+const RATIO_PRECISION: u32 = 256;
+// The table keeps every number up to n, which takes memory quadratic in n,
+// so past this only fast doubling is used.
+const TABLE_LIMIT: u64 = 100_000;
+// F(n) has about 0.7n bits, so anything larger would exhaust memory.
+const MAX_INDEX: u64 = 50_000_000;
+
+fn fill_table(table: &mut Vec<Integer>, n: usize) {
+    if table.is_empty() {
+        table.push(Integer::from(0));
+        table.push(Integer::from(1));
+    }
+    while table.len() <= n {
+        let len = table.len();
+        let next = &table[len - 1] + &table[len - 2];
+        table.push(Integer::from(next));
+    }
+}
+
+// The table is kept between calls, so it only ever grows and anything up to
+// the largest n asked for so far is a lookup.
+fn calculate_fibonacci(table: &mut Vec<Integer>, n: u64) -> String {
+    fill_table(table, n as usize);
+    table[n as usize].to_string()
+}
+
+fn is_cached(table: &[Integer], n: u64) -> bool {
+    (n as usize) < table.len()
+}
+
+// Returns (F(n), F(n+1)) using F(2k) = F(k)(2F(k+1) - F(k)) and
+// F(2k+1) = F(k+1)^2 + F(k)^2, so it takes O(log n) multiplications.
+fn fib_pair(n: u64) -> (Integer, Integer) {
+    if n == 0 {
+        return (Integer::from(0), Integer::from(1));
+    }
+    let (a, b) = fib_pair(n / 2);
+    let c = Integer::from(&a * &(b.clone() * 2u32 - &a));
+    let d = a.square() + b.square();
+    if n.is_multiple_of(2) {
+        (c, d)
+    } else {
+        (d.clone(), c + d)
+    }
+}
+
+fn fib_fast_doubling(n: u64) -> Integer {
+    fib_pair(n).0
+}
+
+fn golden_ratio() -> Float {
+    (Float::with_val(RATIO_PRECISION, 5).sqrt() + 1u32) / 2u32
+}
+
+// Returns F(n+1)/F(n) and its distance from the golden ratio, or None when
+// n is 0 since F(0) is zero and the ratio is undefined.
+fn fibonacci_ratio(table: &mut Vec<Integer>, n: u64) -> Option<(Float, Float)> {
+    if n == 0 {
+        return None;
+    }
+    let n = n as usize;
+    fill_table(table, n + 1);
+    let ratio = Float::with_val(RATIO_PRECISION, &table[n + 1]) / &table[n];
+    let difference = (ratio.clone() - golden_ratio()).abs();
+    Some((ratio, difference))
+}
+
+fn parse_index(input: &str, limit: u64) -> Result<u64, String> {
+    match input.trim().parse::<u64>() {
+        Ok(n) if n > limit => Err(format!(
+            "{} is too large, indices above {} would exhaust memory",
+            n, limit
+        )),
+        Ok(n) => Ok(n),
+        Err(_) => Err(format!(
+            "Invalid number: please enter a whole number from 0 to {}",
+            limit
+        )),
+    }
+}
+
+fn print_ratio(table: &mut Vec<Integer>, arg: &str) {
+    let n = match parse_index(arg, TABLE_LIMIT - 1) {
+        Ok(num) => num,
+        Err(e) => {
+            println!("{}\nUsage: ratio <n>", e);
+            return;
         }
-        return table[n as usize].to_string();
+    };
+    match fibonacci_ratio(table, n) {
+        Some((ratio, difference)) => println!(
+            "F({})/F({}) = {:.40}\nDifference from the golden ratio: {:.5e}",
+            n + 1,
+            n,
+            ratio,
+            difference
+        ),
+        None => println!("The ratio F(1)/F(0) is undefined"),
     }
+}
 
+fn main() {
+    let mut table: Vec<Integer> = Vec::new();
+    // How long each n took the first time it had to be computed.
+    let mut first_times: HashMap<u64, Duration> = HashMap::new();
     loop {
-        print!("Enter a number to calculate the fibonacci number for: ");
+        print!("Enter a number to calculate the fibonacci number for (or exit): ");
         io::stdout().flush().unwrap();
         let mut number = String::new();
 
-        io::stdin()
+        let read = io::stdin()
             .read_line(&mut number)
             .expect("Failed to read line");
+        if read == 0 || number.trim() == "exit" {
+            return;
+        }
 
-        let number: u32 = match number.trim().parse() {
+        if let Some(arg) = number.trim().strip_prefix("ratio") {
+            print_ratio(&mut table, arg);
+            continue;
+        }
+
+        let number = match parse_index(&number, MAX_INDEX) {
             Ok(num) => num,
-            Err(_) => return,
+            Err(e) => {
+                println!("{}", e);
+                continue;
+            }
         };
 
+        if number > TABLE_LIMIT {
+            println!(
+                "Warning: {} is past the table limit of {}, using fast doubling only",
+                number, TABLE_LIMIT
+            );
+            let time = Instant::now();
+            let fibonacci_number = fib_fast_doubling(number);
+            println!(
+                "The fibonacci number is: {}, calculated in {:.2?}",
+                fibonacci_number,
+                time.elapsed()
+            );
+            continue;
+        }
+
+        let cached = is_cached(&table, number);
         let time = Instant::now();
-        let fibonacci_number: String = calculate_fibonacci(number);
+        let fibonacci_number: String = calculate_fibonacci(&mut table, number);
         let elapsed = time.elapsed();
         println!(
             "The fibonacci number is: {}, calculated in {:.2?}",
             fibonacci_number, elapsed
         );
+
+        let time = Instant::now();
+        fib_fast_doubling(number);
+        println!("Fast doubling calculated it in {:.2?}", time.elapsed());
+
+        match first_times.get(&number) {
+            Some(first) if cached => println!(
+                "Cached: first took {:.2?}, {:.1}x faster this time",
+                first,
+                first.as_secs_f64() / elapsed.as_secs_f64().max(1e-9)
+            ),
+            Some(_) => {}
+            None => {
+                first_times.insert(number, elapsed);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ratio_undefined_for_zero() {
+        assert!(fibonacci_ratio(&mut Vec::new(), 0).is_none());
+    }
+
+    #[test]
+    fn test_ratio_converges_to_golden_ratio() {
+        let (_, difference) = fibonacci_ratio(&mut Vec::new(), 100).unwrap();
+        assert!(difference < 1e-40);
+    }
+
+    #[test]
+    fn test_table_grows_monotonically() {
+        let mut table = Vec::new();
+        assert_eq!(calculate_fibonacci(&mut table, 90), "2880067194370816120");
+        assert_eq!(table.len(), 91);
+        assert!(is_cached(&table, 50));
+        assert_eq!(calculate_fibonacci(&mut table, 50), "12586269025");
+        assert_eq!(table.len(), 91);
+        assert!(!is_cached(&table, 100));
+        assert_eq!(
+            calculate_fibonacci(&mut table, 100),
+            "354224848179261915075"
+        );
+        assert_eq!(table.len(), 101);
+    }
+
+    #[test]
+    fn test_fast_doubling_matches_table() {
+        let mut table = Vec::new();
+        for n in 0..=2000 {
+            assert_eq!(
+                fib_fast_doubling(n).to_string(),
+                calculate_fibonacci(&mut table, n)
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_index() {
+        assert_eq!(parse_index(" 42\n", MAX_INDEX), Ok(42));
+        assert_eq!(parse_index("0", 10), Ok(0));
+        assert!(parse_index("11", 10).is_err());
+        assert!(parse_index("-1", 10).is_err());
+        assert!(parse_index("abc", 10).is_err());
+        assert!(parse_index("18446744073709551616", MAX_INDEX).is_err());
     }
 }
@@ -23,6 +23,7 @@ pub struct Library {
     pub books: HashMap<u64, Book>,
 }
 
+#[derive(Debug)]
 pub enum ErrorKind {
     Io(IoError),
     BookNotFound(u64),
@@ -79,6 +80,12 @@ impl Book {
         }
     }
 
+    // Lets books that are already checked out be added as borrowed.
+    pub fn with_available(mut self, available: bool) -> Book {
+        self.available = available;
+        self
+    }
+
     fn change_title(&mut self, title: &str) {
         self.title = title.to_string();
     }
@@ -102,6 +109,23 @@ impl Book {
         self.keywords.retain(|k| k != &keyword);
     }
 
+    // Replaces every keyword in `targets` with `into`, keeping the first
+    // occurrence of any keyword that ends up repeated.
+    fn replace_keywords(&mut self, targets: &[String], into: &str) {
+        let mut keywords: Vec<String> = Vec::new();
+        for keyword in &self.keywords {
+            let keyword = if targets.contains(keyword) {
+                into.to_string()
+            } else {
+                keyword.clone()
+            };
+            if !keywords.contains(&keyword) {
+                keywords.push(keyword);
+            }
+        }
+        self.keywords = keywords;
+    }
+
     fn toggle_availability(&mut self) {
         self.available = !self.available;
     }
@@ -268,6 +292,13 @@ impl Library {
             .collect()
     }
 
+    pub fn untagged_books(&self) -> Vec<&Book> {
+        self.books
+            .values()
+            .filter(|book| book.keywords.is_empty())
+            .collect()
+    }
+
     pub fn change_book_title(&mut self, isbn: u64, title: &str) -> Result<(), ErrorKind> {
         match self.books.get_mut(&isbn) {
             Some(book) => {
@@ -312,6 +343,24 @@ impl Library {
         }
     }
 
+    pub fn rename_keyword(&mut self, old: &str, new: &str) -> usize {
+        self.merge_keywords(vec![old.to_string()], new.to_string())
+    }
+
+    // Returns the number of books whose keywords changed.
+    pub fn merge_keywords(&mut self, targets: Vec<String>, into: String) -> usize {
+        let targets: Vec<String> = targets.iter().map(|k| k.to_lowercase()).collect();
+        let into = into.to_lowercase();
+        let mut changed = 0;
+        for book in self.books.values_mut() {
+            if book.keywords.iter().any(|k| targets.contains(k)) {
+                book.replace_keywords(&targets, &into);
+                changed += 1;
+            }
+        }
+        changed
+    }
+
     pub fn remove_book_keyword(&mut self, isbn: u64, keyword: &str) -> Result<(), ErrorKind> {
         match self.books.get_mut(&isbn) {
             Some(book) => {
@@ -354,3 +403,128 @@ fn format_isbn(isbn: u64) -> String {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keywords(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn test_rename_keyword() {
+        let mut library = Library::default();
+        library
+            .add(Book::new(
+                "Dune",
+                "Frank Herbert",
+                9780441013593,
+                keywords(&["SF", "desert"]),
+            ))
+            .unwrap();
+        library
+            .add(Book::new(
+                "Hyperion",
+                "Dan Simmons",
+                9780553283686,
+                keywords(&["sf"]),
+            ))
+            .unwrap();
+        library
+            .add(Book::new(
+                "Emma",
+                "Jane Austen",
+                9780141439587,
+                keywords(&["romance"]),
+            ))
+            .unwrap();
+
+        assert_eq!(library.rename_keyword("sf", "Science-Fiction"), 2);
+        assert_eq!(
+            library.books[&9780441013593].keywords,
+            keywords(&["science-fiction", "desert"])
+        );
+        assert_eq!(
+            library.books[&9780553283686].keywords,
+            keywords(&["science-fiction"])
+        );
+        assert_eq!(
+            library.books[&9780141439587].keywords,
+            keywords(&["romance"])
+        );
+    }
+
+    #[test]
+    fn test_add_borrowed_book() {
+        let mut library = Library::default();
+        library
+            .add(
+                Book::new("Dune", "Frank Herbert", 9780441013593, Vec::new()).with_available(false),
+            )
+            .unwrap();
+        library
+            .add(Book::new("Emma", "Jane Austen", 9780141439587, Vec::new()))
+            .unwrap();
+
+        let borrowed = library.list_borrowed_books();
+        assert_eq!(borrowed.len(), 1);
+        assert_eq!(borrowed[0].isbn, 9780441013593);
+        assert_eq!(library.list_available_books().len(), 1);
+    }
+
+    #[test]
+    fn test_untagged_books() {
+        let mut library = Library::default();
+        assert!(library.untagged_books().is_empty());
+
+        library
+            .add(Book::new(
+                "Dune",
+                "Frank Herbert",
+                9780441013593,
+                keywords(&["sf"]),
+            ))
+            .unwrap();
+        library
+            .add(Book::new("Emma", "Jane Austen", 9780141439587, Vec::new()))
+            .unwrap();
+
+        let untagged = library.untagged_books();
+        assert_eq!(untagged.len(), 1);
+        assert_eq!(untagged[0].isbn, 9780141439587);
+    }
+
+    #[test]
+    fn test_merge_keywords() {
+        let mut library = Library::default();
+        library
+            .add(Book::new(
+                "Dune",
+                "Frank Herbert",
+                9780441013593,
+                keywords(&["scifi", "sci-fi", "desert"]),
+            ))
+            .unwrap();
+        library
+            .add(Book::new(
+                "Hyperion",
+                "Dan Simmons",
+                9780553283686,
+                keywords(&["Sci-Fi"]),
+            ))
+            .unwrap();
+
+        let merged = library.merge_keywords(keywords(&["scifi", "sci-fi"]), "SF".to_string());
+        assert_eq!(merged, 2);
+        assert_eq!(
+            library.books[&9780441013593].keywords,
+            keywords(&["sf", "desert"])
+        );
+        assert_eq!(library.books[&9780553283686].keywords, keywords(&["sf"]));
+        assert_eq!(
+            library.merge_keywords(keywords(&["scifi"]), "sf".to_string()),
+            0
+        );
+    }
+}
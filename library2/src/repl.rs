@@ -37,6 +37,8 @@ enum Commands {
         isbn: String,
         #[arg(required = false)]
         keywords: Vec<String>,
+        #[arg(long, help = "Add the book as already borrowed")]
+        borrowed: bool,
     },
     #[command(
         arg_required_else_help = true,
@@ -68,8 +70,19 @@ enum Commands {
     AddKeyword { isbn: String, keyword: String },
     #[command(arg_required_else_help = true, alias = "rmkeyword", alias = "rmk", about = "Remove a keyword from a book")]
     RemoveKeyword { isbn: String, keyword: String },
+    #[command(arg_required_else_help = true, alias = "renamek", about = "Rename a keyword across all books")]
+    RenameKeyword { old: String, new: String },
+    #[command(arg_required_else_help = true, alias = "mergek", about = "Merge several keywords into one across all books")]
+    MergeKeywords {
+        #[arg(required = true)]
+        targets: Vec<String>,
+        #[arg(short, long)]
+        into: String,
+    },
     #[command(arg_required_else_help = true, about = "Search for books based on a field")]
     Search(SearchCommands),
+    #[command(about = "List books without any keywords")]
+    Untagged,
     #[command(alias = "ls", alias = "list", about = "List books in the library")]
     ListBooks {
         #[arg(short, long, exclusive(true))]
@@ -220,9 +233,12 @@ fn respond(line: &str, library: &mut Library) -> Result<bool, ErrorKind> {
             author,
             isbn,
             keywords,
+            borrowed,
         } => {
             let isbn = parse_isbn(isbn.as_str())?;
-            library.add(Book::new(title.as_str(), author.as_str(), isbn, keywords))?;
+            let book = Book::new(title.as_str(), author.as_str(), isbn, keywords)
+                .with_available(!borrowed);
+            library.add(book)?;
             Ok(false)
         }
         RemoveBook { isbn } => {
@@ -269,6 +285,16 @@ fn respond(line: &str, library: &mut Library) -> Result<bool, ErrorKind> {
             library.remove_book_keyword(isbn, keyword.as_str())?;
             Ok(false)
         }
+        RenameKeyword { old, new } => {
+            let changed = library.rename_keyword(old.as_str(), new.as_str());
+            println!("Keyword renamed in {} book(s)", changed);
+            Ok(false)
+        }
+        MergeKeywords { targets, into } => {
+            let changed = library.merge_keywords(targets, into);
+            println!("Keywords merged in {} book(s)", changed);
+            Ok(false)
+        }
         Search(args) => {
             match args.search_type {
                 SearchField::Title(SearchArgs { search_terms }) => {
@@ -327,6 +353,16 @@ fn respond(line: &str, library: &mut Library) -> Result<bool, ErrorKind> {
             }
             Ok(false)
         }
+        Untagged => {
+            let books = library.untagged_books();
+            if books.is_empty() {
+                println!("No untagged books");
+            }
+            for book in books {
+                println!("{}", book);
+            }
+            Ok(false)
+        }
         Load { file_path } => {
             Library::load(file_path.as_str(), library).map_err(Library)?;
             Ok(false)
@@ -356,13 +392,7 @@ fn parse_isbn(isbn: &str) -> Result<u64, ErrorKind> {
             .chars()
             .enumerate()
             .map(|(i, c)| match c {
-                'X' => {
-                    if i == 9 {
-                        10
-                    } else {
-                        0
-                    }
-                }
+                'X' if i == 9 => 10,
                 c if c.is_ascii_digit() => c.to_digit(10).unwrap() as i32,
                 _ => 0,
             })
@@ -467,11 +497,12 @@ pub fn run(args: Cli) -> Result<(), ErrorKind> {
                     author,
                     isbn,
                     keywords,
+                    borrowed,
                 } => {
                     let isbn = parse_isbn(isbn.as_str()).map_err(|_| InvalidIsbn)?;
-                    library
-                        .add(Book::new(title.as_str(), author.as_str(), isbn, keywords))
-                        .map_err(Library)?;
+                    let book = Book::new(title.as_str(), author.as_str(), isbn, keywords)
+                        .with_available(!borrowed);
+                    library.add(book).map_err(Library)?;
                     library.save().map_err(Library)?;
                     Ok(())
                 }
@@ -534,6 +565,18 @@ pub fn run(args: Cli) -> Result<(), ErrorKind> {
                     library.save().map_err(Library)?;
                     Ok(())
                 }
+                RenameKeyword { old, new } => {
+                    let changed = library.rename_keyword(old.as_str(), new.as_str());
+                    println!("Keyword renamed in {} book(s)", changed);
+                    library.save().map_err(Library)?;
+                    Ok(())
+                }
+                MergeKeywords { targets, into } => {
+                    let changed = library.merge_keywords(targets, into);
+                    println!("Keywords merged in {} book(s)", changed);
+                    library.save().map_err(Library)?;
+                    Ok(())
+                }
                 Search(args) => {
                     match args.search_type {
                         SearchField::Title(SearchArgs { search_terms }) => {
@@ -589,6 +632,16 @@ pub fn run(args: Cli) -> Result<(), ErrorKind> {
                     }
                     Ok(())
                 }
+                Untagged => {
+                    let books = library.untagged_books();
+                    if books.is_empty() {
+                        println!("No untagged books");
+                    }
+                    for book in books {
+                        println!("{}\n", book);
+                    }
+                    Ok(())
+                }
                 _ => {
                     let mut usage_msg = Vec::new();
                     Cli::command().write_help(&mut usage_msg).unwrap();
@@ -51,6 +51,7 @@ pub struct Library {
     pub catalogue: HashMap<u64, Media>,
 }
 
+#[derive(Debug)]
 pub enum ErrorKind {
     Io(IoError),
     MediaNotFound(u64),
@@ -250,10 +251,6 @@ impl MediaType {
         }
     }
 
-    pub fn to_string(&self) -> String {
-        self.as_str().to_string()
-    }
-
     pub fn new_book(isbn1: Option<u64>, isbn2: Option<u64>) -> MediaType {
         let isbn10: Option<u64>;
         let isbn13: Option<u64>;
@@ -370,7 +367,7 @@ impl MediaType {
                 *isbn10 = Some(isbn);
                 Ok(())
             }
-            media_type => Err(ErrorKind::MediaDoesntHaveIsbn(media_type.to_string())),
+            media_type => Err(ErrorKind::MediaDoesntHaveIsbn(media_type.as_str().to_string())),
         }
     }
 
@@ -384,7 +381,7 @@ impl MediaType {
                 *isbn13 = Some(isbn);
                 Ok(())
             }
-            media_type => Err(ErrorKind::MediaDoesntHaveIsbn(media_type.to_string())),
+            media_type => Err(ErrorKind::MediaDoesntHaveIsbn(media_type.as_str().to_string())),
         }
     }
 
@@ -462,7 +459,7 @@ impl Library {
             match media.media_type {
                 Book { .. } => return Err(ErrorKind::BookIsbnAlreadyExists),
                 AudioBook { .. } => return Err(ErrorKind::BookIsbnAlreadyExists),
-                _ => return Err(ErrorKind::MediaAlreadyExists(media.media_type.to_string())),
+                _ => return Err(ErrorKind::MediaAlreadyExists(media.media_type.as_str().to_string())),
             }
         }
         self.catalogue.insert(media.id, media);
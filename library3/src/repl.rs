@@ -1,4 +1,4 @@
-use clap::{crate_name, ArgAction, Args, Parser, Subcommand};
+use clap::{crate_name, ArgAction, Args, Parser, Subcommand, ValueEnum};
 use std::{
     fmt::{self, Display, Formatter},
     io::{stdin, stdout, Write},
@@ -10,6 +10,8 @@ use ErrorKind::*;
 
 #[derive(Parser, Debug)]
 struct Repl {
+    #[arg(long, global = true, value_enum)]
+    format: Option<OutputFormat>,
     #[command(subcommand)]
     cmd: Commands,
 }
@@ -17,10 +19,19 @@ struct Repl {
 #[derive(Parser, Debug)]
 pub struct Cli {
     library_path: Option<String>,
+    #[arg(long, global = true, value_enum, help = "Output format for get, list and search results")]
+    format: Option<OutputFormat>,
     #[command(subcommand)]
     cmd: Option<Commands>,
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
 #[derive(Subcommand, Debug)]
 enum Commands {
     #[command(subcommand_required = true, about = "Add an item to the library")]
@@ -88,6 +99,7 @@ enum Commands {
     ForceExit,
 }
 
+#[derive(Debug)]
 pub enum ErrorKind {
     Library(LibraryError),
     InvalidCommand(String),
@@ -100,6 +112,7 @@ pub enum ErrorKind {
     CouldNotReadLine,
     FileNotFound,
     InteractiveModeOnly,
+    Serialization(String),
 }
 
 #[derive(Debug, Args)]
@@ -224,6 +237,15 @@ impl ErrorKind {
             CouldNotReadLine => "Could not read line",
             FileNotFound => "Library file not found",
             InteractiveModeOnly => "Command not allowed in non-interactive mode",
+            Serialization(_) => "Could not serialize media",
+        }
+    }
+
+    // I/O failures exit with 1, every other failed command with 2.
+    pub(crate) fn exit_code(&self) -> i32 {
+        match self {
+            Library(LibraryError::Io(_)) | CouldNotReadLine | FileNotFound => 1,
+            _ => 2,
         }
     }
 
@@ -232,6 +254,7 @@ impl ErrorKind {
         match self {
             Library(e) => format!("{}", e),
             InvalidCommand(e) => e.to_string(),
+            Serialization(e) => format!("{}: {}", self.as_str(), e),
             _ => self.as_str().to_string(),
         }
     }
@@ -290,13 +313,7 @@ fn parse_isbn(isbn: &str) -> Result<u64, ErrorKind> {
             .chars()
             .enumerate()
             .map(|(i, c)| match c {
-                'X' => {
-                    if i == 9 {
-                        10
-                    } else {
-                        0
-                    }
-                }
+                'X' if i == 9 => 10,
                 c if c.is_ascii_digit() => c.to_digit(10).unwrap() as i32,
                 _ => 0,
             })
@@ -351,7 +368,29 @@ fn readline() -> Result<String, ErrorKind> {
     }
 }
 
-fn resolve_cmd(cmd: Commands, library: &mut Library) -> Result<bool, ErrorKind> {
+fn format_media_list(media_list: &[&Media], format: OutputFormat) -> Result<String, ErrorKind> {
+    match format {
+        OutputFormat::Text => Ok(media_list
+            .iter()
+            .map(|media| format!("{}\n\n", media))
+            .collect()),
+        OutputFormat::Json => match serde_json::to_string_pretty(media_list) {
+            Ok(json) => Ok(json + "\n"),
+            Err(e) => Err(Serialization(e.to_string())),
+        },
+    }
+}
+
+fn print_media_list(media_list: &[&Media], format: OutputFormat) -> Result<(), ErrorKind> {
+    print!("{}", format_media_list(media_list, format)?);
+    Ok(())
+}
+
+fn resolve_cmd(
+    cmd: Commands,
+    library: &mut Library,
+    format: OutputFormat,
+) -> Result<bool, ErrorKind> {
     use Commands::*;
     match cmd {
         Add(args) => {
@@ -474,31 +513,19 @@ fn resolve_cmd(cmd: Commands, library: &mut Library) -> Result<bool, ErrorKind>
             match args.search_type {
                 SearchField::Title(SearchArgs { search_terms }) => {
                     match library.search_title(search_terms) {
-                        Ok(media_list) => {
-                            for media in media_list {
-                                println!("{}\n", media);
-                            }
-                        }
+                        Ok(media_list) => print_media_list(&media_list, format)?,
                         Err(e) => return Err(Library(e)),
                     }
                 }
                 SearchField::Author(SearchArgs { search_terms }) => {
                     match library.search_author(search_terms) {
-                        Ok(media_list) => {
-                            for media in media_list {
-                                println!("{}\n", media);
-                            }
-                        }
+                        Ok(media_list) => print_media_list(&media_list, format)?,
                         Err(e) => return Err(Library(e)),
                     }
                 }
                 SearchField::Keyword(SearchArgs { search_terms }) => {
                     match library.search_keywords(search_terms) {
-                        Ok(media_list) => {
-                            for media in media_list {
-                                println!("{}\n", media);
-                            }
-                        }
+                        Ok(media_list) => print_media_list(&media_list, format)?,
                         Err(e) => return Err(Library(e)),
                     }
                 }
@@ -513,21 +540,15 @@ fn resolve_cmd(cmd: Commands, library: &mut Library) -> Result<bool, ErrorKind>
                 match available {
                     Some(true) => {
                         let media_list = library.list_available_from_type(&media_type);
-                        for media in media_list {
-                            println!("{}\n", media);
-                        }
+                        print_media_list(&media_list, format)?;
                     }
                     Some(false) => {
                         let media_list = library.list_borrowed_from_type(&media_type);
-                        for media in media_list {
-                            println!("{}\n", media);
-                        }
+                        print_media_list(&media_list, format)?;
                     }
                     _ => {
                         let media_list = library.list_media_type(&media_type);
-                        for media in media_list {
-                            println!("{}\n", media);
-                        }
+                        print_media_list(&media_list, format)?;
                     }
                 }
                 return Ok(false);
@@ -536,21 +557,15 @@ fn resolve_cmd(cmd: Commands, library: &mut Library) -> Result<bool, ErrorKind>
             match available {
                 Some(true) => {
                     let media_list = library.list_available();
-                    for media in media_list {
-                        println!("{}\n", media);
-                    }
+                    print_media_list(&media_list, format)?;
                 }
                 Some(false) => {
                     let media_list = library.list_borrowed();
-                    for media in media_list {
-                        println!("{}\n", media);
-                    }
+                    print_media_list(&media_list, format)?;
                 }
                 _ => {
                     let media_list = library.list();
-                    for media in media_list {
-                        println!("{}\n", media);
-                    }
+                    print_media_list(&media_list, format)?;
                 }
             }
             Ok(false)
@@ -577,10 +592,10 @@ fn resolve_cmd(cmd: Commands, library: &mut Library) -> Result<bool, ErrorKind>
     }
 }
 
-fn respond(line: &str, library: &mut Library) -> Result<bool, ErrorKind> {
+fn respond(line: &str, library: &mut Library, format: OutputFormat) -> Result<bool, ErrorKind> {
     let args = shlex::split(line).ok_or(InvalidQuoting)?;
     let cli = Repl::try_parse_from(args)?;
-    resolve_cmd(cli.cmd, library)
+    resolve_cmd(cli.cmd, library, cli.format.unwrap_or(format))
 }
 
 fn confirm_exit() -> Result<bool, ErrorKind> {
@@ -597,14 +612,14 @@ fn confirm_exit() -> Result<bool, ErrorKind> {
     }
 }
 
-fn run_repl(library: &mut Library) -> Result<(), ErrorKind> {
+fn run_repl(library: &mut Library, format: OutputFormat) -> Result<(), ErrorKind> {
     loop {
         let line = readline()?;
         if line.is_empty() {
             continue;
         }
 
-        match respond(&line, library) {
+        match respond(&line, library, format) {
             Ok(true) => break,
             Ok(false) => continue,
             Err(e) => {
@@ -619,6 +634,7 @@ fn run_repl(library: &mut Library) -> Result<(), ErrorKind> {
 pub fn run(args: Cli) -> Result<(), ErrorKind> {
     use Commands::*;
     let mut library = Library::default();
+    let format = args.format.unwrap_or_default();
 
     if let Some(file_path) = &args.library_path {
         let name = file_path.as_str();
@@ -647,12 +663,12 @@ pub fn run(args: Cli) -> Result<(), ErrorKind> {
                 Exit => Err(InteractiveModeOnly),
                 ForceExit => Err(InteractiveModeOnly),
                 _ => {
-                    resolve_cmd(cli, &mut library)?;
+                    resolve_cmd(cli, &mut library, format)?;
                     Ok(())
                 }
             }
         } else {
-            run_repl(&mut library)?;
+            run_repl(&mut library, format)?;
             Ok(())
         }
     } else {
@@ -664,7 +680,31 @@ pub fn run(args: Cli) -> Result<(), ErrorKind> {
                 Err(e) => return Err(Library(e)),
             }
         }
-        run_repl(&mut library)?;
+        run_repl(&mut library, format)?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_format_emits_media_objects() {
+        let book = MediaType::new_book(Some(9780306406157), None);
+        let media = Media::new(1, "Dune".to_string(), "Herbert".to_string(), None, book, vec![]);
+
+        let text = format_media_list(&[&media], OutputFormat::Text).unwrap();
+        assert!(text.starts_with("ID: 1\nTitle: Dune"));
+
+        let json = format_media_list(&[&media], OutputFormat::Json).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value[0]["title"], "Dune");
+        assert_eq!(value[0]["id"], 1);
+    }
+
+    #[test]
+    fn test_serialization_error_exits_with_2() {
+        assert_eq!(Serialization("key must be a string".to_string()).exit_code(), 2);
+    }
+}
@@ -0,0 +1,26 @@
+use std::{env, fs, process::Command};
+
+fn exit_code(args: &[&str]) -> Option<i32> {
+    Command::new(env!("CARGO_BIN_EXE_library"))
+        .args(args)
+        .output()
+        .ok()
+        .and_then(|output| output.status.code())
+}
+
+#[test]
+fn missing_library_file_exits_with_1() {
+    assert_eq!(exit_code(&["./no-such-library.json"]), Some(1));
+}
+
+#[test]
+fn failed_command_exits_with_2() {
+    let path = env::temp_dir().join(format!("library3-exit-{}.json", std::process::id()));
+    let path = path.to_str().unwrap();
+    let json = format!(r#"{{"name":"Test","file_path":"{}","catalogue":{{}}}}"#, path);
+    fs::write(path, json).unwrap();
+
+    let code = exit_code(&[path, "remove", "42"]);
+    fs::remove_file(path).unwrap();
+    assert_eq!(code, Some(2));
+}
@@ -1,7 +1,8 @@
+use crate::log::Log;
 use serde::{Deserialize, Serialize};
 use serde_json;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fmt::{self, Display, Formatter},
     fs::File,
     io::{BufReader, Error as IoError, Write},
@@ -40,6 +41,8 @@ pub struct Media {
     pub author: String,
     pub year: Option<u16>,
     pub available: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub borrower: Option<String>,
     pub media_type: MediaType,
     pub keywords: Vec<String>,
 }
@@ -49,10 +52,14 @@ pub struct Library {
     pub name: String,
     pub file_path: String,
     pub catalogue: HashMap<u64, Media>,
+    #[serde(skip)]
+    pub log: Log,
 }
 
+#[derive(Debug)]
 pub enum ErrorKind {
     Io(IoError),
+    Serialization(String),
     MediaNotFound(u64),
     IsbnNotFound(u64),
     MediaNotAvailable(u64),
@@ -75,6 +82,7 @@ impl ErrorKind {
         use ErrorKind::*;
         match self {
             Io(e) => e.to_string(),
+            Serialization(e) => format!("Malformed JSON: {}", e),
             MediaNotFound(id) => format!("Media with ID {} not found", id),
             IsbnNotFound(isbn) => format!("Media with ISBN {} not found", isbn),
             MediaNotAvailable(id) => format!("Media with ID {} is not available", id),
@@ -126,14 +134,18 @@ impl Display for &Media {
                 year,
                 self.available,
                 self.keywords
-            )
+            )?;
         } else {
             write!(
                 f,
                 "ID: {}\nTitle: {}\nAuthor: {}\n{}Available: {}\nKeywords: {:?}",
                 self.id, self.title, self.author, &self.media_type, self.available, self.keywords
-            )
+            )?;
         }
+        if let Some(borrower) = &self.borrower {
+            write!(f, "\nBorrower: {}", borrower)?;
+        }
+        Ok(())
     }
 }
 
@@ -215,6 +227,7 @@ impl Media {
             title,
             author,
             available: true,
+            borrower: None,
             year,
             keywords: keywords
                 .iter()
@@ -251,10 +264,31 @@ impl Media {
         self.keywords.retain(|k| k != &keyword);
     }
 
+    fn normalize_keywords(&mut self) -> bool {
+        let mut keywords: Vec<String> = Vec::new();
+        for keyword in &self.keywords {
+            let keyword = keyword.trim().to_lowercase();
+            if !keyword.is_empty() && !keywords.contains(&keyword) {
+                keywords.push(keyword);
+            }
+        }
+        if keywords == self.keywords {
+            false
+        } else {
+            self.keywords = keywords;
+            true
+        }
+    }
+
     fn toggle_availability(&mut self) {
         self.available = !self.available;
     }
 
+    fn lend_to(&mut self, borrower: Option<&str>) {
+        self.available = false;
+        self.borrower = borrower.map(|b| b.to_string());
+    }
+
     pub fn dimensions(&self) -> Result<String, ErrorKind> {
         match &self.media_type {
             Sculpture {
@@ -321,6 +355,35 @@ impl Media {
         }
     }
 
+    // Every field, one per line, with N/A for the ones this item doesn't have.
+    pub fn dump(&self) -> String {
+        let (isbn10, isbn13) = match &self.media_type {
+            Book { isbn10, isbn13 } | AudioBook { isbn10, isbn13, .. } => (*isbn10, *isbn13),
+            _ => (None, None),
+        };
+        let field = |value: Result<String, ErrorKind>, name: &str| {
+            value.unwrap_or_else(|_| format!("{}: N/A\n", name))
+        };
+        let year = self.year.map_or("N/A".to_string(), |year| year.to_string());
+        let borrower = self.borrower.as_deref().unwrap_or("N/A");
+        format!(
+            "ID: {}\nType: {}\nTitle: {}\nAuthor: {}\n{}\n{}\n{}{}{}Year: {}\nAvailable: {}\nBorrower: {}\nKeywords: {:?}",
+            self.id,
+            self.type_as_str(),
+            self.title,
+            self.author,
+            isbn10.map_or("ISBN-10: N/A".to_string(), format_isbn),
+            isbn13.map_or("ISBN-13: N/A".to_string(), format_isbn),
+            field(self.duration(), "Duration"),
+            field(self.material(), "Material"),
+            field(self.dimensions(), "Dimensions"),
+            year,
+            self.available,
+            borrower,
+            self.keywords
+        )
+    }
+
     pub fn material(&self) -> Result<String, ErrorKind> {
         match &self.media_type {
             Sculpture { material, .. } => {
@@ -356,6 +419,11 @@ impl MediaType {
         self.as_str().to_string()
     }
 
+    // Compares ignoring case and spaces, so "audiobook" matches "Audio Book".
+    pub fn matches_name(&self, name: &str) -> bool {
+        self.as_str().replace(" ", "").to_lowercase() == name.replace(" ", "").to_lowercase()
+    }
+
     pub fn new_book(isbn1: Option<u64>, isbn2: Option<u64>) -> MediaType {
         let isbn10: Option<u64>;
         let isbn13: Option<u64>;
@@ -521,6 +589,7 @@ impl Library {
             name: name.to_string(),
             catalogue: HashMap::new(),
             file_path: file_path.to_string(),
+            log: Log::default(),
         }
     }
 
@@ -531,7 +600,7 @@ impl Library {
                     Ok(_) => Ok(()),
                     Err(e) => Err(ErrorKind::Io(e)),
                 },
-                Err(e) => Err(ErrorKind::Io(e.into())),
+                Err(e) => Err(ErrorKind::Serialization(e.to_string())),
             },
             Err(e) => Err(ErrorKind::Io(e)),
         }
@@ -552,18 +621,41 @@ impl Library {
                         library.file_path = loaded.file_path;
                         Ok(library)
                     }
-                    Err(e) => Err(ErrorKind::Io(e.into())),
+                    Err(e) => Err(ErrorKind::Serialization(e.to_string())),
                 }
             }
             Err(e) => Err(ErrorKind::Io(e)),
         }
     }
 
+    pub fn export_item(&self, id: u64, file_path: &str) -> Result<(), ErrorKind> {
+        let media = self.get(id)?;
+        let json = serde_json::to_string_pretty(media)
+            .map_err(|e| ErrorKind::Serialization(e.to_string()))?;
+        let mut file = File::create(file_path)?;
+        file.write_all(json.as_bytes())?;
+        Ok(())
+    }
+
+    // Adds the item stored at file_path, giving it a fresh ID if its own is
+    // already taken, and returns the ID it was added with.
+    pub fn import_item(&mut self, file_path: &str) -> Result<u64, ErrorKind> {
+        let reader = BufReader::new(File::open(file_path)?);
+        let mut media: Media =
+            serde_json::from_reader(reader).map_err(|e| ErrorKind::Serialization(e.to_string()))?;
+        if self.catalogue.contains_key(&media.id) {
+            media.id = self.catalogue.keys().max().map_or(0, |id| id + 1);
+        }
+        let id = media.id;
+        self.add(media)?;
+        Ok(id)
+    }
+
     pub fn add(&mut self, media: Media) -> Result<(), ErrorKind> {
         if self.contains(&media) {
             match media.media_type {
                 Book { .. } => return Err(ErrorKind::BookIsbnAlreadyExists),
-                AudioBook { .. } => return Err(ErrorKind::BookIsbnAlreadyExists),
+                AudioBook { .. } => return Err(ErrorKind::AudioBookIsbnAlreadyExists),
                 _ => {
                     return Err(ErrorKind::MediaAlreadyExists(
                         media.media_type.type_to_string(),
@@ -582,6 +674,12 @@ impl Library {
         }
     }
 
+    // Only empties the in-memory catalogue, the file keeps its contents
+    // until the library is saved again.
+    pub fn clear_catalogue(&mut self) {
+        self.catalogue.clear();
+    }
+
     pub fn list(&self) -> Vec<&Media> {
         self.catalogue.values().collect()
     }
@@ -589,10 +687,7 @@ impl Library {
     pub fn list_media_type(&self, media_type: &str) -> Vec<&Media> {
         self.catalogue
             .values()
-            .filter(|media| {
-                media.media_type.as_str().replace(" ", "").to_lowercase()
-                    == media_type.replace(" ", "").to_lowercase()
-            })
+            .filter(|media| media.media_type.matches_name(media_type))
             .collect()
     }
 
@@ -606,10 +701,7 @@ impl Library {
     pub fn list_available_from_type(&self, media_type: &str) -> Vec<&Media> {
         self.catalogue
             .values()
-            .filter(|media| {
-                media.available
-                    && media.media_type.as_str().to_lowercase() == media_type.to_lowercase()
-            })
+            .filter(|media| media.available && media.media_type.matches_name(media_type))
             .collect()
     }
 
@@ -623,10 +715,43 @@ impl Library {
     pub fn list_borrowed_from_type(&self, media_type: &str) -> Vec<&Media> {
         self.catalogue
             .values()
-            .filter(|media| !media.available && media.media_type.as_str() == media_type)
+            .filter(|media| !media.available && media.media_type.matches_name(media_type))
             .collect()
     }
 
+    // Sorted by id so cleanup can work through them in a stable order.
+    pub fn list_unyeared(&self) -> Vec<&Media> {
+        let mut media_list: Vec<&Media> = self
+            .catalogue
+            .values()
+            .filter(|media| media.year.is_none())
+            .collect();
+        media_list.sort_by_key(|media| media.id);
+        media_list
+    }
+
+    // Other items sharing at least one keyword with the given one, most shared
+    // keywords first and then by id. Keywords are compared ignoring case.
+    pub fn related_to(&self, id: u64) -> Vec<(&Media, usize)> {
+        let keywords: HashSet<String> = match self.catalogue.get(&id) {
+            Some(media) => media.keywords.iter().map(|k| k.to_lowercase()).collect(),
+            None => return Vec::new(),
+        };
+        let mut related: Vec<(&Media, usize)> = self
+            .catalogue
+            .values()
+            .filter(|media| media.id != id)
+            .map(|media| {
+                let own: HashSet<String> =
+                    media.keywords.iter().map(|k| k.to_lowercase()).collect();
+                (media, own.intersection(&keywords).count())
+            })
+            .filter(|(_, shared)| *shared > 0)
+            .collect();
+        related.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.id.cmp(&b.0.id)));
+        related
+    }
+
     pub fn contains(&self, media: &Media) -> bool {
         match media.media_type {
             Book { isbn10, isbn13 } => {
@@ -660,11 +785,11 @@ impl Library {
         }
     }
 
-    pub fn borrow(&mut self, id: u64) -> Result<(), ErrorKind> {
+    pub fn borrow(&mut self, id: u64, borrower: Option<&str>) -> Result<(), ErrorKind> {
         match self.catalogue.get_mut(&id) {
             Some(book) => {
                 if book.available {
-                    book.toggle_availability();
+                    book.lend_to(borrower);
                     Ok(())
                 } else {
                     Err(ErrorKind::MediaNotAvailable(id))
@@ -674,6 +799,16 @@ impl Library {
         }
     }
 
+    pub fn force_borrow(&mut self, id: u64, borrower: Option<&str>) -> Result<(), ErrorKind> {
+        match self.catalogue.get_mut(&id) {
+            Some(book) => {
+                book.lend_to(borrower);
+                Ok(())
+            }
+            None => Err(ErrorKind::MediaNotFound(id)),
+        }
+    }
+
     pub fn return_media(&mut self, id: u64) -> Result<(), ErrorKind> {
         match self.catalogue.get_mut(&id) {
             Some(book) => {
@@ -681,6 +816,7 @@ impl Library {
                     Err(ErrorKind::MediaAlreadyAvailable(id))
                 } else {
                     book.toggle_availability();
+                    book.borrower = None;
                     Ok(())
                 }
             }
@@ -817,6 +953,52 @@ impl Library {
         }
     }
 
+    pub fn normalize_all_keywords(&mut self) -> usize {
+        self.catalogue
+            .values_mut()
+            .map(|media| media.normalize_keywords())
+            .filter(|changed| *changed)
+            .count()
+    }
+
+    pub fn validate(&self) -> Vec<(u64, String)> {
+        let this_year = current_year();
+        let mut issues = Vec::new();
+        for media in self.catalogue.values() {
+            match &media.media_type {
+                Book {
+                    isbn10: None,
+                    isbn13: None,
+                }
+                | AudioBook {
+                    isbn10: None,
+                    isbn13: None,
+                    ..
+                } => issues.push((media.id, format!("{} has no ISBN", media.type_as_str()))),
+                Sculpture {
+                    height,
+                    width,
+                    depth,
+                    weight,
+                    ..
+                } if [height, width, depth, weight].contains(&&0) => {
+                    issues.push((media.id, "Sculpture has a zero dimension".to_string()))
+                }
+                Paiting { height, width, .. } if *height == 0 || *width == 0 => {
+                    issues.push((media.id, "Painting has a zero dimension".to_string()))
+                }
+                _ => {}
+            }
+            if let Some(year) = media.year {
+                if year > this_year {
+                    issues.push((media.id, format!("Year {} is in the future", year)));
+                }
+            }
+        }
+        issues.sort();
+        issues
+    }
+
     pub fn get(&self, id: u64) -> Result<&Media, ErrorKind> {
         match self.catalogue.get(&id) {
             Some(media) => Ok(media),
@@ -859,6 +1041,7 @@ impl Default for Library {
             name: "Library".to_string(),
             catalogue: HashMap::new(),
             file_path: "library.json".to_string(),
+            log: Log::default(),
         }
     }
 }
@@ -867,6 +1050,29 @@ pub fn is_isbn13(isbn: u64) -> bool {
     isbn.checked_ilog10() == Some(12)
 }
 
+pub fn current_year() -> u16 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    let since_the_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    year_from_days(since_the_epoch.as_secs() / 86_400)
+}
+
+// Gregorian year of a day counted from 1970-01-01, using Howard Hinnant's
+// days-to-civil algorithm on 400-year eras starting in March.
+fn year_from_days(days: u64) -> u16 {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    // Month indexes 10 and 11 are January and February of the next year.
+    let year = era * 400 + year_of_era + u64::from(month_index >= 10);
+    year as u16
+}
+
 fn format_isbn(isbn: u64) -> String {
     let isbn_str = isbn.to_string();
     if is_isbn13(isbn) {
@@ -895,3 +1101,248 @@ fn format_duration(duration: u32) -> String {
     let seconds = duration % 60;
     format!("{:02}:{:02}:{:02}", hours, minutes, seconds)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn media(id: u64, media_type: MediaType, keywords: &[&str]) -> Media {
+        Media::new(
+            id,
+            format!("Title {}", id),
+            "Author".to_string(),
+            None,
+            media_type,
+            keywords.iter().map(|k| k.to_string()).collect(),
+        )
+    }
+
+    fn book(id: u64, isbn: u64, keywords: &[&str]) -> Media {
+        media(id, MediaType::new_book(Some(isbn), None), keywords)
+    }
+
+    #[test]
+    fn test_normalize_all_keywords() {
+        let mut library = Library::default();
+        let mut messy = book(1, 9780306406157, &[]);
+        messy.keywords = vec![
+            " Fantasy ".to_string(),
+            "FANTASY".to_string(),
+            "magic".to_string(),
+            "  ".to_string(),
+        ];
+        library.add(messy).unwrap();
+        library.add(book(2, 9780140449136, &["classic"])).unwrap();
+
+        assert_eq!(library.normalize_all_keywords(), 1);
+        assert_eq!(library.catalogue[&1].keywords, vec!["fantasy", "magic"]);
+        assert_eq!(library.catalogue[&2].keywords, vec!["classic"]);
+        assert_eq!(library.normalize_all_keywords(), 0);
+    }
+
+    #[test]
+    fn test_duplicate_audio_book_error() {
+        let mut library = Library::default();
+        let audio_book =
+            |id| media(id, MediaType::new_audio_book(600, Some(9780306406157), None), &[]);
+        library.add(audio_book(1)).unwrap();
+        match library.add(audio_book(2)) {
+            Err(e) => assert_eq!(e.details(), "Audio Book with that ISBN already exists"),
+            Ok(()) => panic!("duplicate audio book was added"),
+        }
+        match library.add(book(3, 9780306406157, &[])) {
+            Ok(()) => {}
+            Err(e) => panic!(
+                "book sharing an audio book's ISBN was rejected: {}",
+                e.details()
+            ),
+        }
+        match library.add(book(4, 9780306406157, &[])) {
+            Err(e) => assert_eq!(e.details(), "Book with that ISBN already exists"),
+            Ok(()) => panic!("duplicate book was added"),
+        }
+    }
+
+    #[test]
+    fn test_list_unyeared_and_set_year() {
+        let mut library = Library::default();
+        assert!(library.list_unyeared().is_empty());
+        library.add(book(2, 9780306406157, &[])).unwrap();
+        library.add(book(1, 9780140449136, &[])).unwrap();
+        let mut dated = book(3, 9780141439518, &[]);
+        dated.year = Some(1813);
+        library.add(dated).unwrap();
+
+        let ids: Vec<u64> = library.list_unyeared().iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![1, 2]);
+
+        assert!(library.change_year(2, 1961).is_ok());
+        assert_eq!(library.catalogue[&2].year, Some(1961));
+        let ids: Vec<u64> = library.list_unyeared().iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![1]);
+        assert!(matches!(
+            library.change_year(9, 2000),
+            Err(ErrorKind::MediaNotFound(9))
+        ));
+    }
+
+    #[test]
+    fn test_force_borrow_overrides_availability() {
+        let mut library = Library::default();
+        library.add(book(1, 9780306406157, &[])).unwrap();
+
+        assert!(library.borrow(1, Some("alice")).is_ok());
+        assert!(matches!(
+            library.borrow(1, Some("bob")),
+            Err(ErrorKind::MediaNotAvailable(1))
+        ));
+        assert_eq!(library.catalogue[&1].borrower.as_deref(), Some("alice"));
+
+        assert!(library.force_borrow(1, Some("bob")).is_ok());
+        assert!(!library.catalogue[&1].available);
+        assert_eq!(library.catalogue[&1].borrower.as_deref(), Some("bob"));
+    }
+
+    #[test]
+    fn test_media_type_filters_agree() {
+        let mut library = Library::default();
+        let audio_book = MediaType::new_audio_book(3600, Some(9780306406157), None);
+        library.add(media(1, audio_book, &[])).unwrap();
+        library.add(book(2, 9780140449136, &[])).unwrap();
+
+        assert_eq!(library.list_media_type("audiobook").len(), 1);
+        assert_eq!(library.list_available_from_type("audiobook").len(), 1);
+        assert_eq!(library.list_borrowed_from_type("audiobook").len(), 0);
+
+        library.borrow(1, None).unwrap();
+        assert_eq!(library.list_available_from_type("Audio Book").len(), 0);
+        assert_eq!(library.list_borrowed_from_type("AUDIOBOOK").len(), 1);
+    }
+
+    #[test]
+    fn test_validate_reports_each_problem() {
+        let mut library = Library::default();
+        let mut clean = book(1, 9780306406157, &[]);
+        clean.year = Some(1999);
+        library.catalogue.insert(1, clean);
+        let no_isbn = MediaType::new_book(None, None);
+        library.catalogue.insert(2, media(2, no_isbn, &[]));
+        let mut future = book(3, 9780140449136, &[]);
+        future.year = Some(3000);
+        library.catalogue.insert(3, future);
+        let sculpture = MediaType::new_sculpture(10, 0, 10, 10, None);
+        library.catalogue.insert(4, media(4, sculpture, &[]));
+        let painting = MediaType::new_painting(0, 10, None);
+        library.catalogue.insert(5, media(5, painting, &[]));
+
+        let ids: Vec<u64> = library.validate().iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![2, 3, 4, 5]);
+
+        library.catalogue.retain(|id, _| *id == 1);
+        assert!(library.validate().is_empty());
+    }
+
+    #[test]
+    fn test_export_and_import_item() {
+        let path = std::env::temp_dir().join(format!("library4-item-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut library = Library::default();
+        library.add(book(1, 9780306406157, &["physics"])).unwrap();
+        assert!(library.export_item(1, path).is_ok());
+
+        let mut other = Library::default();
+        other.add(book(1, 9780140449136, &[])).unwrap();
+        let id = other.import_item(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_ne!(id, 1);
+        let imported = &other.catalogue[&id];
+        assert_eq!(imported.title, "Title 1");
+        assert_eq!(imported.keywords, vec!["physics"]);
+        assert!(imported.media_type.check_isbn(9780306406157));
+    }
+
+    #[test]
+    fn test_load_reports_malformed_file() {
+        let path =
+            std::env::temp_dir().join(format!("library4-corrupt-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        std::fs::write(path, "{\"name\": \"broken\", \"catalogue\": [").unwrap();
+        let mut library = Library::default();
+        let result = Library::load(path, &mut library).map(|_| ());
+        std::fs::remove_file(path).unwrap();
+
+        match result {
+            Err(ErrorKind::Serialization(e)) => assert!(!e.is_empty()),
+            Err(e) => panic!("expected a serialization error, got {}", e),
+            Ok(()) => panic!("corrupt library file was loaded"),
+        }
+        assert!(matches!(
+            Library::load(path, &mut library).map(|_| ()),
+            Err(ErrorKind::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_dump_book() {
+        let mut media = book(1, 9780306406157, &["physics", "classic"]);
+        media.year = Some(1999);
+        let dump = media.dump();
+        for line in [
+            "Title: Title 1",
+            "Author: Author",
+            "ISBN-10: N/A",
+            "ISBN-13: 978-0-30640-615-7",
+            "Duration: N/A",
+            "Dimensions: N/A",
+            "Year: 1999",
+            "Borrower: N/A",
+            "Keywords: [\"physics\", \"classic\"]",
+        ] {
+            assert!(
+                dump.lines().any(|l| l.ends_with(line)),
+                "missing {:?} in\n{}",
+                line,
+                dump
+            );
+        }
+
+        media.year = None;
+        assert!(media.dump().contains("Year: N/A"));
+    }
+
+    #[test]
+    fn test_related_to() {
+        let mut library = Library::default();
+        for media in [
+            book(1, 9780306406157, &["fantasy", "magic", "dragons"]),
+            book(2, 9780140449136, &["dragons"]),
+            book(3, 9780451524935, &["Magic", "fantasy"]),
+            book(4, 9780061120084, &["history"]),
+        ] {
+            library.add(media).unwrap();
+        }
+
+        let related: Vec<(u64, usize)> = library
+            .related_to(1)
+            .iter()
+            .map(|(media, shared)| (media.id, *shared))
+            .collect();
+        assert_eq!(related, vec![(3, 2), (2, 1)]);
+
+        library.add(book(5, 9780743273565, &[])).unwrap();
+        assert!(library.related_to(5).is_empty());
+        assert!(library.related_to(42).is_empty());
+    }
+
+    #[test]
+    fn test_year_from_days_at_year_boundaries() {
+        assert_eq!(year_from_days(0), 1970);
+        assert_eq!(year_from_days(364), 1970);
+        assert_eq!(year_from_days(365), 1971);
+        assert_eq!(year_from_days(11_322), 2000);
+        assert_eq!(year_from_days(11_323), 2001);
+        assert_eq!(year_from_days(20_088), 2024);
+        assert_eq!(year_from_days(20_089), 2025);
+    }
+}
@@ -1,5 +1,6 @@
 pub mod repl;
 pub mod library;
+pub mod log;
 
 use crate::repl::{Cli, run};
 use clap::Parser;
@@ -11,7 +12,7 @@ fn main() {
         Ok(_) => (),
         Err(e) => {
             eprintln!("{}", e);
-            std::process::exit(1);
+            std::process::exit(e.exit_code());
         }
     }
 }
@@ -1,11 +1,11 @@
 use clap::{crate_name, ArgAction, Args, Parser, Subcommand};
 use std::{
     fmt::{self, Display, Formatter},
-    io::{stdin, stdout, Write},
+    io::{stdin, stdout, BufRead, Write},
     path::Path,
 };
 
-use crate::library::{ErrorKind as LibraryError, Library, Media, MediaType};
+use crate::library::{current_year, ErrorKind as LibraryError, Library, Media, MediaType};
 use ErrorKind::*;
 
 #[derive(Parser, Debug)]
@@ -33,8 +33,15 @@ enum Commands {
     Remove { id: u64 },
     #[command(subcommand_required = true, about = "Change a book's details")]
     Change(ChangeCommands),
-    #[command(arg_required_else_help = true, about = "Borrow a book")]
-    Borrow { id: u64 },
+    #[command(arg_required_else_help = true, about = "Borrow one or more books")]
+    Borrow {
+        #[arg(required = true)]
+        ids: Vec<u64>,
+        #[arg(short, long, help = "Who is borrowing the items")]
+        borrower: Option<String>,
+        #[arg(short, long, help = "Borrow even if the item is not available")]
+        force: bool,
+    },
     #[command(arg_required_else_help = true, about = "Return a book")]
     Return { id: u64 },
     #[command(
@@ -51,6 +58,34 @@ enum Commands {
         about = "Remove a keyword from a book"
     )]
     RemoveKeyword { id: u64, keyword: String },
+    #[command(
+        alias = "normalize",
+        about = "Trim, lowercase and deduplicate the keywords of every item"
+    )]
+    NormalizeKeywords,
+    #[command(about = "Report missing ISBNs, future years and zero dimensions")]
+    Validate,
+    #[command(about = "List items without a year")]
+    Unyeared,
+    #[command(
+        arg_required_else_help = true,
+        about = "List items sharing keywords with an item, most shared first"
+    )]
+    Related { id: u64 },
+    #[command(arg_required_else_help = true, about = "Set the year of an item")]
+    SetYear { id: u64, year: u16 },
+    #[command(
+        arg_required_else_help = true,
+        alias = "export",
+        about = "Save a single item to a JSON file"
+    )]
+    ExportItem { id: u64, file_path: String },
+    #[command(
+        arg_required_else_help = true,
+        alias = "import",
+        about = "Add an item from a JSON file exported with export-item"
+    )]
+    ImportItem { file_path: String },
     #[command(
         subcommand_required = true,
         about = "Search for books based on a field"
@@ -80,6 +115,10 @@ enum Commands {
         about = "Load the library (interactive mode only)"
     )]
     Load { file_path: String },
+    #[command(about = "Walk through the basic commands using a sample library")]
+    Tutorial,
+    #[command(about = "Remove every item from the library (interactive mode only)")]
+    Clear,
     #[command(alias = "q", about = "Save and exit (interactive mode only)")]
     Exit,
     #[command(
@@ -110,6 +149,7 @@ pub enum ErrorKind {
     MissingArgs,
     YearNotFound,
     MediaDoesntHaveIsbn,
+    BorrowsFailed(usize),
 }
 
 #[derive(Debug, Args)]
@@ -169,6 +209,8 @@ pub enum GetField {
     Material(GetArgs),
     Dimensions(GetArgs),
     Id(GetIdArgs),
+    #[command(about = "Every field of an item, with N/A for missing ones")]
+    All(GetArgs),
 }
 
 #[derive(Debug, Args)]
@@ -249,7 +291,7 @@ pub struct BookArgs {
 pub struct AudiobookArgs {
     title: String,
     author: String,
-    #[arg(help = "Should be in the format HH:MM:SS")]
+    #[arg(help = "Should be in the format HH:MM:SS, MM:SS or seconds")]
     duration: String,
     #[arg(name = "ISBN-13", required = true)]
     isbn1: String,
@@ -311,6 +353,18 @@ impl ErrorKind {
             MissingArgs => "Missing arguments",
             YearNotFound => "Year not found",
             MediaDoesntHaveIsbn => "Media doesn't have an ISBN",
+            BorrowsFailed(_) => "Some items could not be borrowed",
+        }
+    }
+
+    // I/O failures and malformed files exit with 1, every other failed
+    // command with 2.
+    pub(crate) fn exit_code(&self) -> i32 {
+        match self {
+            Library(LibraryError::Io(_) | LibraryError::Serialization(_))
+            | CouldNotReadLine
+            | FileNotFound => 1,
+            _ => 2,
         }
     }
 
@@ -319,6 +373,7 @@ impl ErrorKind {
         match self {
             Library(e) => format!("{}", e),
             InvalidCommand(e) => e.to_string(),
+            BorrowsFailed(count) => format!("{} of the items could not be borrowed", count),
             _ => self.as_str().to_string(),
         }
     }
@@ -355,19 +410,39 @@ fn generate_id() -> u64 {
     since_the_epoch.as_secs()
 }
 
+// Accepts "SS" (seconds only), "MM:SS" or "HH:MM:SS". A two-part duration is
+// always read as minutes and seconds, so "1:30" is 90 seconds.
 fn parse_duration(duration: &str) -> Result<u32, ErrorKind> {
     let time: Vec<&str> = duration.split(':').collect();
-    if time.len() == 2 {
-        let hours = time[0].parse::<u32>().map_err(|_| InvalidDuration)?;
-        let minutes = time[1].parse::<u32>().map_err(|_| InvalidDuration)?;
-        return Ok(hours * 3600 + minutes * 60);
-    } else if time.len() == 3 {
-        let hours = time[0].parse::<u32>().map_err(|_| InvalidDuration)?;
-        let minutes = time[1].parse::<u32>().map_err(|_| InvalidDuration)?;
-        let seconds = time[2].parse::<u32>().map_err(|_| InvalidDuration)?;
-        return Ok(hours * 3600 + minutes * 60 + seconds);
+    let parse = |part: &str| part.parse::<u32>().map_err(|_| InvalidDuration);
+    match time.len() {
+        1 => parse(time[0]),
+        2 => {
+            let minutes = parse(time[0])?;
+            let seconds = parse(time[1])?;
+            Ok(minutes * 60 + seconds)
+        }
+        3 => {
+            let hours = parse(time[0])?;
+            let minutes = parse(time[1])?;
+            let seconds = parse(time[2])?;
+            Ok(hours * 3600 + minutes * 60 + seconds)
+        }
+        _ => Err(InvalidDuration),
     }
-    Err(InvalidCommand("Invalid duration".to_string()))
+}
+
+fn list_summary(media_list: &[&Media]) -> String {
+    if media_list.is_empty() {
+        return "No items match.".to_string();
+    }
+    let available = media_list.iter().filter(|media| media.available).count();
+    format!(
+        "Showing {} items ({} available, {} borrowed)",
+        media_list.len(),
+        available,
+        media_list.len() - available
+    )
 }
 
 fn parse_isbn(isbn: &str) -> Result<u64, ErrorKind> {
@@ -377,13 +452,7 @@ fn parse_isbn(isbn: &str) -> Result<u64, ErrorKind> {
             .chars()
             .enumerate()
             .map(|(i, c)| match c {
-                'X' => {
-                    if i == 9 {
-                        10
-                    } else {
-                        0
-                    }
-                }
+                'X' if i == 9 => 10,
                 c if c.is_ascii_digit() => c.to_digit(10).unwrap() as i32,
                 _ => 0,
             })
@@ -515,7 +584,7 @@ fn resolve_cmd(cmd: Commands, library: &mut Library) -> Result<bool, ErrorKind>
             };
             match library.add(media) {
                 Ok(_) => {
-                    println!("Media added with ID: {}", id);
+                    library.log.info(format!("Media added with ID: {}", id));
                     Ok(false)
                 }
                 Err(e) => Err(Library(e)),
@@ -544,6 +613,24 @@ fn resolve_cmd(cmd: Commands, library: &mut Library) -> Result<bool, ErrorKind>
         Get(args) => {
             use GetField::*;
             match args.get_field {
+                All(GetArgs { id, title, author, isbn }) => {
+                    let media = if let Some(id) = id {
+                        library.get(id)
+                    } else if let (Some(title), Some(author)) = (title, author) {
+                        library.get_by_title(&title, &author)
+                    } else if let Some(isbn) = isbn {
+                        library.get_by_isbn(parse_isbn(isbn.as_str())?)
+                    } else {
+                        return Err(MissingArgs);
+                    };
+                    match media {
+                        Ok(media) => {
+                            println!("{}", media.dump());
+                            Ok(false)
+                        }
+                        Err(e) => Err(Library(e)),
+                    }
+                }
                 Title(GetTitleArgs { id, isbn }) => {
                     if let Some(id) = id {
                         match library.get(id) {
@@ -833,9 +920,33 @@ fn resolve_cmd(cmd: Commands, library: &mut Library) -> Result<bool, ErrorKind>
                 }
             }
         }
-        Borrow { id } => {
-            library.borrow(id)?;
-            Ok(false)
+        Borrow {
+            ids,
+            borrower,
+            force,
+        } => {
+            let mut results = borrow_many(library, &ids, borrower.as_deref(), force);
+            if results.len() == 1 {
+                let (_, result) = results.remove(0);
+                result?;
+                return Ok(false);
+            }
+            let mut failed = 0;
+            for (id, result) in results {
+                match result {
+                    Ok(_) => library.log.info(format!("{}: borrowed", id)),
+                    Err(e) => {
+                        library.log.error(format!("{}: {}", id, e));
+                        failed += 1;
+                    }
+                }
+            }
+            // Every result is reported first, then any failure fails the
+            // whole command like a single borrow would.
+            match failed {
+                0 => Ok(false),
+                _ => Err(BorrowsFailed(failed)),
+            }
         }
         Return { id } => {
             library.return_media(id)?;
@@ -849,6 +960,58 @@ fn resolve_cmd(cmd: Commands, library: &mut Library) -> Result<bool, ErrorKind>
             library.remove_keyword(id, keyword.as_str())?;
             Ok(false)
         }
+        NormalizeKeywords => {
+            let changed = library.normalize_all_keywords();
+            library.log.info(format!("Normalized keywords on {} item(s)", changed));
+            Ok(false)
+        }
+        Validate => {
+            let issues = library.validate();
+            if issues.is_empty() {
+                println!("No issues found");
+            }
+            for (id, issue) in issues {
+                println!("{}: {}", id, issue);
+            }
+            Ok(false)
+        }
+        Unyeared => {
+            let media_list = library.list_unyeared();
+            if media_list.is_empty() {
+                println!("Every item has a year");
+            }
+            for media in media_list {
+                println!("{}\n", media);
+            }
+            Ok(false)
+        }
+        Related { id } => {
+            library.get(id)?;
+            let related = library.related_to(id);
+            if related.is_empty() {
+                println!("No items share a keyword with {}", id);
+            }
+            for (media, shared) in related {
+                println!("{}\nShared keywords: {}\n", media, shared);
+            }
+            Ok(false)
+        }
+        SetYear { id, year } => {
+            library.change_year(id, year)?;
+            if year > current_year() {
+                library.log.info(format!("Warning: year {} is in the future", year));
+            }
+            Ok(false)
+        }
+        ExportItem { id, file_path } => {
+            library.export_item(id, &file_path)?;
+            Ok(false)
+        }
+        ImportItem { file_path } => {
+            let id = library.import_item(&file_path)?;
+            library.log.info(format!("Media added with ID: {}", id));
+            Ok(false)
+        }
         Search(args) => {
             match args.search_type {
                 SearchField::Title(SearchArgs { search_terms }) => {
@@ -888,50 +1051,18 @@ fn resolve_cmd(cmd: Commands, library: &mut Library) -> Result<bool, ErrorKind>
             available,
             media_type,
         } => {
-            if let Some(media_type) = media_type {
-                match available {
-                    Some(true) => {
-                        let media_list = library.list_available_from_type(&media_type);
-                        for media in media_list {
-                            println!("{}\n", media);
-                        }
-                    }
-                    Some(false) => {
-                        let media_list = library.list_borrowed_from_type(&media_type);
-                        for media in media_list {
-                            println!("{}\n", media);
-                        }
-                    }
-                    _ => {
-                        let media_list = library.list_media_type(&media_type);
-                        for media in media_list {
-                            println!("{}\n", media);
-                        }
-                    }
-                }
-                return Ok(false);
-            }
-
-            match available {
-                Some(true) => {
-                    let media_list = library.list_available();
-                    for media in media_list {
-                        println!("{}\n", media);
-                    }
-                }
-                Some(false) => {
-                    let media_list = library.list_borrowed();
-                    for media in media_list {
-                        println!("{}\n", media);
-                    }
-                }
-                _ => {
-                    let media_list = library.list();
-                    for media in media_list {
-                        println!("{}\n", media);
-                    }
-                }
+            let media_list = match (media_type, available) {
+                (Some(media_type), Some(true)) => library.list_available_from_type(&media_type),
+                (Some(media_type), Some(false)) => library.list_borrowed_from_type(&media_type),
+                (Some(media_type), None) => library.list_media_type(&media_type),
+                (None, Some(true)) => library.list_available(),
+                (None, Some(false)) => library.list_borrowed(),
+                (None, None) => library.list(),
+            };
+            for media in &media_list {
+                println!("{}\n", media);
             }
+            println!("{}", list_summary(&media_list));
             Ok(false)
         }
         Load { file_path } => {
@@ -945,6 +1076,15 @@ fn resolve_cmd(cmd: Commands, library: &mut Library) -> Result<bool, ErrorKind>
             library.save().map_err(Library)?;
             Ok(false)
         }
+        Tutorial => {
+            let mut sample = Library::default();
+            run_tutorial(&mut sample, Some(&mut stdin().lock()))?;
+            Ok(false)
+        }
+        Clear => {
+            clear_library(library, &mut stdin().lock())?;
+            Ok(false)
+        }
         Exit => match confirm_exit() {
             Ok(true) => {
                 library.save().map_err(Library)?;
@@ -956,6 +1096,26 @@ fn resolve_cmd(cmd: Commands, library: &mut Library) -> Result<bool, ErrorKind>
     }
 }
 
+// Tries every id even after a failure, so one unavailable item doesn't stop
+// the rest of the stack from being checked out.
+fn borrow_many(
+    library: &mut Library,
+    ids: &[u64],
+    borrower: Option<&str>,
+    force: bool,
+) -> Vec<(u64, Result<(), LibraryError>)> {
+    ids.iter()
+        .map(|&id| {
+            let result = if force {
+                library.force_borrow(id, borrower)
+            } else {
+                library.borrow(id, borrower)
+            };
+            (id, result)
+        })
+        .collect()
+}
+
 fn respond(line: &str, library: &mut Library) -> Result<bool, ErrorKind> {
     let args = shlex::split(line).ok_or(InvalidQuoting)?;
     let cli = Repl::try_parse_from(args)?;
@@ -963,19 +1123,108 @@ fn respond(line: &str, library: &mut Library) -> Result<bool, ErrorKind> {
 }
 
 fn confirm_exit() -> Result<bool, ErrorKind> {
-    print!("Are you sure you want to exit? (y/n): ");
+    confirm("Are you sure you want to exit?", &mut stdin().lock())
+}
+
+// Asks until the answer is y or n. Running out of input counts as a no.
+fn confirm(question: &str, input: &mut impl BufRead) -> Result<bool, ErrorKind> {
+    print!("{} (y/n): ", question);
     stdout().flush().unwrap();
     let mut buffer = String::new();
-    match stdin().read_line(&mut buffer) {
+    match input.read_line(&mut buffer) {
+        Ok(0) => Ok(false),
         Ok(_) => match buffer.trim() {
             "y" => Ok(true),
             "n" => Ok(false),
-            _ => confirm_exit(),
+            _ => confirm(question, input),
         },
         Err(_) => Err(CouldNotReadLine),
     }
 }
 
+fn clear_library(library: &mut Library, input: &mut impl BufRead) -> Result<(), ErrorKind> {
+    let question = format!(
+        "Remove all {} items from the library?",
+        library.catalogue.len()
+    );
+    if confirm(&question, input)? {
+        library.clear_catalogue();
+        println!("Library cleared, save to make it permanent");
+    } else {
+        println!("Nothing was removed");
+    }
+    Ok(())
+}
+
+// Each step is run against the library given to run_tutorial; "{id}" is
+// replaced with the id of the sample book once it has been added.
+const TUTORIAL_STEPS: [(&str, &str); 5] = [
+    (
+        "Add a book by giving its title, author and ISBN-13",
+        "add book \"Tutorial Sample\" \"Sample Author\" 9780141439518",
+    ),
+    ("List every item in the library", "list"),
+    (
+        "Borrow the book, optionally naming who has it",
+        "borrow {id} --borrower tutorial",
+    ),
+    ("List only the items that are borrowed", "list --available false"),
+    ("Return the book so it is available again", "return {id}"),
+];
+
+enum TutorialAction {
+    Run,
+    Skip,
+    Quit,
+}
+
+fn tutorial_prompt(input: &mut dyn BufRead) -> Result<TutorialAction, ErrorKind> {
+    print!("Press Enter to run it, 's' to skip it or 'q' to leave the tutorial: ");
+    stdout().flush().unwrap();
+    let mut buffer = String::new();
+    match input.read_line(&mut buffer) {
+        Ok(0) => Ok(TutorialAction::Quit),
+        Ok(_) => match buffer.trim() {
+            "" => Ok(TutorialAction::Run),
+            "s" => Ok(TutorialAction::Skip),
+            "q" => Ok(TutorialAction::Quit),
+            _ => tutorial_prompt(input),
+        },
+        Err(_) => Err(CouldNotReadLine),
+    }
+}
+
+// Without input every step runs, otherwise each one asks first. Steps that
+// need the sample book are left out when its step was skipped.
+fn run_tutorial(
+    library: &mut Library,
+    mut input: Option<&mut dyn BufRead>,
+) -> Result<(), ErrorKind> {
+    println!("Tutorial: the steps below run on a sample library, your own library is not changed");
+    for (i, (description, command)) in TUTORIAL_STEPS.iter().enumerate() {
+        println!("\nStep {}/{}: {}", i + 1, TUTORIAL_STEPS.len(), description);
+        let command = match library.catalogue.keys().next() {
+            Some(id) => command.replace("{id}", &id.to_string()),
+            None if command.contains("{id}") => {
+                println!("  Skipped, it needs the sample book added in step 1");
+                continue;
+            }
+            None => command.to_string(),
+        };
+        println!("  {}", command);
+        if let Some(input) = input.as_deref_mut() {
+            match tutorial_prompt(input)? {
+                TutorialAction::Run => {}
+                TutorialAction::Skip => continue,
+                TutorialAction::Quit => return Ok(()),
+            }
+        }
+        respond(&format!("{} {}", crate_name!(), command), library)?;
+    }
+    println!("\nTutorial finished");
+    Ok(())
+}
+
 fn run_repl(library: &mut Library) -> Result<(), ErrorKind> {
     loop {
         let line = readline()?;
@@ -987,7 +1236,7 @@ fn run_repl(library: &mut Library) -> Result<(), ErrorKind> {
             Ok(true) => break,
             Ok(false) => continue,
             Err(e) => {
-                eprintln!("{}", e);
+                library.log.error(e.to_string());
                 continue;
             }
         }
@@ -1024,6 +1273,8 @@ pub fn run(args: Cli) -> Result<(), ErrorKind> {
                 Save { .. } => Err(InteractiveModeOnly),
                 Load { .. } => Err(InteractiveModeOnly),
                 Exit => Err(InteractiveModeOnly),
+                Tutorial => Err(InteractiveModeOnly),
+                Clear => Err(InteractiveModeOnly),
                 ForceExit => Err(InteractiveModeOnly),
                 _ => {
                     resolve_cmd(cli, &mut library)?;
@@ -1047,3 +1298,156 @@ pub fn run(args: Cli) -> Result<(), ErrorKind> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::{CaptureLogger, Log};
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn test_clear_asks_for_confirmation() {
+        let mut library = Library::default();
+        let line = "library add book Dune \"Frank Herbert\" 9780441013593";
+        assert!(respond(line, &mut library).is_ok());
+        assert_eq!(library.catalogue.len(), 1);
+
+        assert!(clear_library(&mut library, &mut "n\n".as_bytes()).is_ok());
+        assert_eq!(library.catalogue.len(), 1);
+
+        assert!(clear_library(&mut library, &mut "maybe\ny\n".as_bytes()).is_ok());
+        assert!(library.catalogue.is_empty());
+    }
+
+    #[test]
+    fn test_list_summary() {
+        let mut library = Library::default();
+        let line = "library add book Dune \"Frank Herbert\" 9780441013593";
+        assert!(respond(line, &mut library).is_ok());
+        let mut borrowed = library.list()[0].clone();
+        for id in 2..=3 {
+            borrowed.id = id;
+            borrowed.available = false;
+            library.catalogue.insert(id, borrowed.clone());
+        }
+
+        assert_eq!(
+            list_summary(&library.list()),
+            "Showing 3 items (1 available, 2 borrowed)"
+        );
+        assert_eq!(
+            list_summary(&library.list_borrowed()),
+            "Showing 2 items (0 available, 2 borrowed)"
+        );
+        assert_eq!(
+            list_summary(&library.list_media_type("Painting")),
+            "No items match."
+        );
+    }
+
+    #[test]
+    fn test_parse_duration_formats() {
+        assert_eq!(parse_duration("90").ok(), Some(90));
+        assert_eq!(parse_duration("1:30").ok(), Some(90));
+        assert_eq!(parse_duration("1:30:00").ok(), Some(5400));
+        assert!(parse_duration("1:x").is_err());
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("1:2:3:4").is_err());
+    }
+
+    #[test]
+    fn test_set_year_warns_about_future_years() {
+        let mut library = Library::default();
+        let capture = Rc::new(RefCell::new(CaptureLogger::default()));
+        library.log = Log::new(capture.clone());
+        let line = "library add book Dune \"Frank Herbert\" 9780441013593";
+        assert!(respond(line, &mut library).is_ok());
+        let id = *library.catalogue.keys().next().unwrap();
+        assert_eq!(library.list_unyeared().len(), 1);
+
+        assert!(respond(&format!("library set-year {} 1965", id), &mut library).is_ok());
+        assert_eq!(library.catalogue[&id].year, Some(1965));
+        assert!(library.list_unyeared().is_empty());
+        assert_eq!(capture.borrow().messages.len(), 1);
+
+        assert!(respond(&format!("library set-year {} 9999", id), &mut library).is_ok());
+        assert_eq!(
+            capture.borrow().messages.last().map(String::as_str),
+            Some("Warning: year 9999 is in the future")
+        );
+        assert!(respond("library set-year 1 2000", &mut library).is_err());
+    }
+
+    #[test]
+    fn test_log_captures_messages() {
+        let mut library = Library::default();
+        let capture = Rc::new(RefCell::new(CaptureLogger::default()));
+        library.log = Log::new(capture.clone());
+
+        let line = "library add painting Sunflowers \"Van Gogh\" 92 73";
+        assert!(respond(line, &mut library).is_ok());
+        let id = *library.catalogue.keys().next().unwrap();
+        assert_eq!(
+            capture.borrow().messages,
+            vec![format!("Media added with ID: {}", id)]
+        );
+
+        if let Err(e) = respond("library return 1", &mut library) {
+            library.log.error(e.to_string());
+        }
+        assert_eq!(capture.borrow().errors.len(), 1);
+        assert!(capture.borrow().errors[0].contains("ID 1"));
+    }
+
+    #[test]
+    fn test_borrow_many_reports_each_id() {
+        let mut library = Library::default();
+        let capture = Rc::new(RefCell::new(CaptureLogger::default()));
+        library.log = Log::new(capture.clone());
+        let ids = [1, 2, 3];
+        let isbns = [9780441013593, 9780141439587, 9780141182803];
+        for (&id, isbn) in ids.iter().zip(isbns) {
+            let book = MediaType::new_book(Some(isbn), None);
+            let title = format!("Title {}", id);
+            let media = Media::new(id, title, "Author".to_string(), None, book, vec![]);
+            library.add(media).unwrap();
+        }
+        assert!(library.borrow(ids[1], None).is_ok());
+
+        let results = borrow_many(&mut library, &ids, Some("alice"), false);
+        assert!(results[0].1.is_ok());
+        assert!(matches!(
+            results[1].1,
+            Err(LibraryError::MediaNotAvailable(id)) if id == ids[1]
+        ));
+        assert!(results[2].1.is_ok());
+        assert_eq!(library.list_borrowed().len(), 3);
+        assert_eq!(library.catalogue[&ids[1]].borrower, None);
+
+        let line = format!("library borrow {} {} 42", ids[0], ids[2]);
+        assert!(matches!(respond(&line, &mut library), Err(BorrowsFailed(3))));
+        assert_eq!(capture.borrow().errors.len(), 3);
+        assert!(capture.borrow().errors[2].starts_with("42: "));
+    }
+
+    #[test]
+    fn test_tutorial_runs_on_fresh_library() {
+        let mut library = Library::default();
+        assert!(run_tutorial(&mut library, None).is_ok());
+        assert_eq!(library.catalogue.len(), 1);
+        let media = library.catalogue.values().next().unwrap();
+        assert_eq!(media.title, "Tutorial Sample");
+        assert!(media.available);
+        assert_eq!(media.borrower, None);
+    }
+
+    #[test]
+    fn test_tutorial_with_sample_book_skipped() {
+        let mut library = Library::default();
+        // Steps 3 and 5 need the book, so only three steps ask.
+        let mut input = "s\n\n\n".as_bytes();
+        assert!(run_tutorial(&mut library, Some(&mut input)).is_ok());
+        assert!(library.catalogue.is_empty());
+        assert!(input.is_empty());
+    }
+}
@@ -1,12 +1,15 @@
-use chrono::NaiveDate;
+use chrono::{Local, NaiveDate};
 use std::{
+    collections::BTreeMap,
     fmt::{self, Display, Formatter},
-    fs::File,
+    fs::{self, File},
     io::{self, BufReader, Write},
+    path::{Path, PathBuf},
 };
 
 use crate::{
-    product::{ErrorKind as ProductError, Product, ProductList},
+    product::{format_price, ErrorKind as ProductError, Product, ProductList, SortKey},
+    log::Log,
     warehouse::{ErrorKind as WarehouseError, Warehouse},
 };
 use serde::{Deserialize, Serialize};
@@ -14,12 +17,17 @@ use serde_json;
 
 use ErrorKind::*;
 
+const FILE_PREFIX: &str = "storage-";
+const FILE_EXTENSION: &str = ".json";
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Storage {
     pub name: String,
     pub product_list: ProductList,
     pub file_path: String,
     pub warehouse: Warehouse,
+    #[serde(default)]
+    pub removal_log: Vec<(NaiveDate, u32, usize)>,
 }
 
 #[derive(Debug)]
@@ -85,20 +93,41 @@ impl ErrorKind {
 #[allow(dead_code)]
 impl Storage {
     pub fn new(name: String, file_path: Option<String>) -> Self {
-        let default_path = format!("./storage_{}.json", name);
+        let default_path = Storage::default_file_path(&name);
         Storage {
             name,
             product_list: ProductList::new(),
             warehouse: Warehouse::new(),
             file_path: file_path.unwrap_or(default_path),
+            removal_log: Vec::new(),
         }
     }
 
+    pub fn default_file_path(name: &str) -> String {
+        format!("./{}{}{}", FILE_PREFIX, name, FILE_EXTENSION)
+    }
+
+    pub fn find_storage_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let is_storage_file = match path.file_name().and_then(|name| name.to_str()) {
+                Some(name) => name.starts_with(FILE_PREFIX) && name.ends_with(FILE_EXTENSION),
+                None => false,
+            };
+            if is_storage_file && path.is_file() {
+                files.push(path);
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
     pub fn save(&self) -> io::Result<()> {
         match File::create(&self.file_path) {
             Ok(mut file) => match serde_json::to_string_pretty(self) {
                     Ok(json) => file.write_all(json.as_bytes()),
-                    Err(e) => Err(io::Error::new(io::ErrorKind::Other, e)),
+                    Err(e) => Err(io::Error::other(e)),
                 },
             Err(e) => Err(e),
         }
@@ -116,8 +145,11 @@ impl Storage {
                     Ok(new_storage) => {
                         storage.name = new_storage.name;
                         storage.product_list = new_storage.product_list;
+                        let log = std::mem::take(&mut storage.warehouse.log);
                         storage.warehouse = new_storage.warehouse;
+                        storage.warehouse.log = log;
                         storage.file_path = new_storage.file_path;
+                        storage.removal_log = new_storage.removal_log;
 
                         Ok(storage)
                     }
@@ -128,6 +160,14 @@ impl Storage {
         }
     }
 
+    pub fn log(&self) -> &Log {
+        &self.warehouse.log
+    }
+
+    pub fn set_quiet(&mut self, quiet: bool) {
+        self.warehouse.log.quiet = quiet;
+    }
+
     pub fn check_capacity(&self) -> usize {
         self.warehouse.capacity
     }
@@ -137,8 +177,54 @@ impl Storage {
     }
 
     pub fn list_products(&self) {
-        for product in self.product_list.products.values() {
-            println!("{}", product);
+        print!("{}", self.product_list);
+    }
+
+    pub fn list_products_by(&self, key: SortKey) {
+        print!("{}", self.product_list.table_by(key));
+    }
+
+    pub fn describe_product(&self, id: u32) -> Result<String, ErrorKind> {
+        let product = match self.product_list.get(id) {
+            Some(product) => product,
+            None => return Err(ProductNotFound),
+        };
+        let positions = self.warehouse.find_all_item_occurences(id);
+        let mut dates: Vec<NaiveDate> = positions
+            .iter()
+            .filter_map(|&(row, col, zone)| {
+                self.warehouse
+                    .get_item(row, col, zone)
+                    .and_then(|item| item.expiry_date)
+            })
+            .collect();
+        dates.sort();
+        let date_or_na = |date: Option<&NaiveDate>| match date {
+            Some(date) => date.to_string(),
+            None => "N/A".to_string(),
+        };
+        let contiguous = match self.warehouse.is_product_stored_contiguously(id) {
+            true => "yes",
+            false => "no",
+        };
+
+        Ok(format!(
+            "Product: {}\n ID: {}\n Price: {}\n Quantity: {}\n Zones used: {}\n Stored contiguously: {}\n Earliest expiry: {}\n Latest expiry: {}",
+            product.name,
+            product.id,
+            format_price(product.price),
+            product.quantity,
+            positions.len(),
+            contiguous,
+            date_or_na(dates.first()),
+            date_or_na(dates.last()),
+        ))
+    }
+
+    pub fn describe_product_by_name(&self, name: &str) -> Result<String, ErrorKind> {
+        match self.find_product_id(name) {
+            Some(id) => self.describe_product(id),
+            None => Err(ProductNotFound),
         }
     }
 
@@ -146,7 +232,10 @@ impl Storage {
         let id = self.product_list.products.len() as u32 + 1;
         let product = Product::new(id, name.clone(), price, 0);
         match self.product_list.add(product) {
-            Ok(_) => Ok(()),
+            Ok(_) => {
+                self.log().info(format!("Product {} added", id));
+                Ok(())
+            }
             Err(e) => Err(ProductError(e)),
         }
     }
@@ -174,7 +263,7 @@ impl Storage {
                 Err(HasStock)
             } else {
                 self.product_list.products.remove(&id);
-                println!("Product {} removed", id);
+                self.warehouse.log.info(format!("Product {} removed", id));
                 Ok(())
             }
         } else {
@@ -224,28 +313,84 @@ impl Storage {
         expiration_date: Option<NaiveDate>,
     ) -> Result<(), ErrorKind> {
         match self.find_product_id(name) {
-            Some(id) => match self.restock_product(id, quantity, expiration_date) {
-                Ok(_) => match self.product_list.products.get_mut(&id) {
-                    Some(product) => {
-                        product.quantity += quantity;
-                        Ok(())
-                    }
-                    None => Err(ProductNotFound),
-                },
-                Err(e) => Err(e),
-            },
+            Some(id) => self.restock_product(id, quantity, expiration_date),
             None => Err(ProductNotFound),
         }
     }
 
+    pub fn update_product(
+        &mut self,
+        id: u32,
+        new_name: Option<String>,
+        new_id: Option<u32>,
+    ) -> Result<(), ErrorKind> {
+        if !self.product_list.products.contains_key(&id) {
+            return Err(ProductNotFound);
+        }
+        if let Some(name) = &new_name {
+            if self
+                .product_list
+                .products
+                .values()
+                .any(|p| p.id != id && &p.name == name)
+            {
+                return Err(ProductError(ProductError::NameExists));
+            }
+        }
+        if let Some(new_id) = new_id {
+            if new_id != id && self.product_list.products.contains_key(&new_id) {
+                return Err(ProductError(ProductError::IDExists));
+            }
+        }
+
+        match self.product_list.products.remove(&id) {
+            Some(mut product) => {
+                if let Some(name) = new_name {
+                    product.name = name;
+                }
+                if let Some(new_id) = new_id {
+                    product.id = new_id;
+                    self.warehouse.change_product_id(id, new_id);
+                }
+                self.warehouse.log.info(format!("Product {} updated: {}", id, product));
+                self.product_list.products.insert(product.id, product);
+                Ok(())
+            }
+            None => Err(ProductNotFound),
+        }
+    }
+
+    // Moves up to `quantity` items from one product to another without
+    // relocating them, returning how many items were relabelled.
+    pub fn retag_items(
+        &mut self,
+        from_id: u32,
+        to_id: u32,
+        quantity: usize,
+    ) -> Result<usize, ErrorKind> {
+        if !self.product_list.products.contains_key(&from_id)
+            || !self.product_list.products.contains_key(&to_id)
+        {
+            return Err(ProductNotFound);
+        }
+        let retagged = self.warehouse.retag_items(from_id, to_id, quantity);
+        if let Some(product) = self.product_list.get_mut(from_id) {
+            product.quantity = product.quantity.saturating_sub(retagged);
+        }
+        if let Some(product) = self.product_list.get_mut(to_id) {
+            product.add_quantity(retagged);
+        }
+        Ok(retagged)
+    }
+
     pub fn change_price(&mut self, id: u32, price: u64) -> Result<(), ErrorKind> {
         let current_price = self.product_list.products.get(&id).unwrap().price;
         if let Some(product) = self.product_list.products.get_mut(&id) {
             product.set_price(price);
-            println!(
+            self.warehouse.log.info(format!(
                 "Price for product {} changed from {} to {}",
                 id, current_price, price
-            );
+            ));
             Ok(())
         } else {
             Err(ProductNotFound)
@@ -259,12 +404,91 @@ impl Storage {
         }
     }
 
+    pub fn segregate_product(&mut self, id: u32) -> Result<(), ErrorKind> {
+        if !self.product_list.products.contains_key(&id) {
+            return Err(ProductNotFound);
+        }
+        match self.warehouse.segregate_items_by_expiration(id) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(WarehouseError(e)),
+        }
+    }
+
+    pub fn set_reorder_level(&mut self, id: u32, level: Option<usize>) -> Result<(), ErrorKind> {
+        match self.product_list.get_mut(id) {
+            Some(product) => {
+                product.set_reorder_level(level);
+                Ok(())
+            }
+            None => Err(ProductNotFound),
+        }
+    }
+
+    // Suggests restocking every product at or below its reorder level up to
+    // twice that level, handing out the free warehouse space in id order.
+    pub fn reorder_suggestions(&self) -> Vec<(u32, usize)> {
+        let mut products: Vec<&Product> = self
+            .product_list
+            .products
+            .values()
+            .filter(|product| product.needs_reorder())
+            .collect();
+        products.sort_by_key(|product| product.id);
+
+        let mut remaining_space = self.warehouse.available_space;
+        let mut suggestions = Vec::new();
+        for product in products {
+            let target = product.reorder_level.unwrap_or(0) * 2;
+            let quantity = target.saturating_sub(product.quantity).min(remaining_space);
+            remaining_space -= quantity;
+            suggestions.push((product.id, quantity));
+        }
+        suggestions
+    }
+
+    // Zones each product occupies and the percentage of the warehouse
+    // capacity they represent, in id order.
+    pub fn footprint(&self) -> Vec<(u32, usize, f64)> {
+        let mut ids: Vec<&u32> = self.product_list.products.keys().collect();
+        ids.sort();
+        ids.into_iter()
+            .map(|id| {
+                let zones = self.warehouse.find_all_item_occurences(*id).len();
+                let percentage = match self.warehouse.capacity {
+                    0 => 0.0,
+                    capacity => zones as f64 / capacity as f64 * 100.0,
+                };
+                (*id, zones, percentage)
+            })
+            .collect()
+    }
+
+    // Minimum, maximum and average days until expiry over the dated units of a
+    // product. Expired units count with a negative number of days.
+    pub fn shelf_life(&self, id: u32, today: NaiveDate) -> Option<(i64, i64, f64)> {
+        let days: Vec<i64> = self
+            .warehouse
+            .find_all_item_occurences(id)
+            .into_iter()
+            .filter_map(|(row, col, zone)| self.warehouse.get_item(row, col, zone))
+            .filter_map(|item| item.expiry_date)
+            .map(|date| (date - today).num_days())
+            .collect();
+        let min = *days.iter().min()?;
+        let max = *days.iter().max()?;
+        let average = days.iter().sum::<i64>() as f64 / days.len() as f64;
+        Some((min, max, average))
+    }
+
     pub fn remove_stock(&mut self, id: u32, quantity: usize) -> Result<(), ErrorKind> {
         match self.product_list.products.get(&id) {
             Some(_) => match self.warehouse.remove_item_by_qty(id, quantity) {
-                Ok(_) => match self.product_list.products.get_mut(&id) {
+                // The warehouse can remove more or fewer items than asked for,
+                // so the stock and the log follow what it actually removed.
+                Ok(removed) => match self.product_list.products.get_mut(&id) {
                     Some(product) => {
-                        product.quantity -= quantity;
+                        product.quantity = product.quantity.saturating_sub(removed);
+                        self.log_removal(id, removed);
                         Ok(())
                     }
                     None => Err(ProductNotFound),
@@ -275,6 +499,21 @@ impl Storage {
         }
     }
 
+    fn log_removal(&mut self, id: u32, quantity: usize) {
+        let today = Local::now().date_naive();
+        self.removal_log.push((today, id, quantity));
+    }
+
+    pub fn removal_summary(&self, since: Option<NaiveDate>) -> Vec<(u32, usize)> {
+        let mut summary: BTreeMap<u32, usize> = BTreeMap::new();
+        for (date, id, quantity) in &self.removal_log {
+            if since.is_none_or(|since| *date >= since) {
+                *summary.entry(*id).or_default() += quantity;
+            }
+        }
+        summary.into_iter().collect()
+    }
+
     pub fn remove_stock_by_name(&mut self, name: &str, quantity: usize) -> Result<(), ErrorKind> {
         match self.find_product_id(name) {
             Some(id) => self.remove_stock(id, quantity),
@@ -287,7 +526,9 @@ impl Storage {
             Some(_) => match self.warehouse.remove_all_items(id) {
                 Ok(_) => match self.product_list.products.get_mut(&id) {
                     Some(product) => {
+                        let quantity = product.quantity;
                         product.quantity = 0;
+                        self.log_removal(id, quantity);
                         Ok(())
                     }
                     None => Err(ProductNotFound),
@@ -308,3 +549,243 @@ impl Storage {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{log::CaptureLogger, product::ProductItem};
+    use std::{cell::RefCell, rc::Rc};
+
+    fn test_storage() -> Storage {
+        let mut storage = Storage::new("test".to_string(), None);
+        storage.warehouse.initialize_rows(2, 2, 5);
+        storage
+    }
+
+    #[test]
+    fn test_default_file_path() {
+        assert_eq!(Storage::default_file_path("a"), "./storage-a.json");
+        let storage = Storage::new("market".to_string(), None);
+        assert_eq!(storage.file_path, "./storage-market.json");
+    }
+
+    #[test]
+    fn test_find_storage_files() {
+        let dir = std::env::temp_dir().join(format!("storage-scan-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["storage-b.json", "storage-a.json", "notes.json", "storage-c.txt"] {
+            File::create(dir.join(name)).unwrap();
+        }
+
+        let files = Storage::find_storage_files(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            files,
+            vec![dir.join("storage-a.json"), dir.join("storage-b.json")]
+        );
+    }
+
+    #[test]
+    fn test_describe_product() {
+        let mut storage = test_storage();
+        storage.new_product("milk".to_string(), 250).unwrap();
+        let dates = ["2025-03-01", "2025-01-15", "2025-02-10"];
+        for date in dates {
+            let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+            storage.restock_product(1, 2, Some(date)).unwrap();
+        }
+
+        let description = storage.describe_product_by_name("milk").unwrap();
+        for field in [
+            "Product: milk",
+            "ID: 1",
+            "Price: $2.50",
+            "Quantity: 6",
+            "Zones used: 6",
+            "Stored contiguously: no",
+            "Earliest expiry: 2025-01-15",
+            "Latest expiry: 2025-03-01",
+        ] {
+            assert!(description.contains(field), "missing {:?}", field);
+        }
+        assert!(matches!(storage.describe_product(9), Err(ProductNotFound)));
+    }
+
+    #[test]
+    fn test_removal_log() {
+        let mut storage = test_storage();
+        storage.new_product("milk".to_string(), 250).unwrap();
+        storage.new_product("eggs".to_string(), 300).unwrap();
+        let date = NaiveDate::from_ymd_opt(2025, 1, 1);
+        storage.restock_product(1, 5, date).unwrap();
+        storage.restock_product(2, 3, date).unwrap();
+
+        storage.remove_stock(1, 2).unwrap();
+        storage.remove_stock(1, 1).unwrap();
+        storage.empty_stock(2).unwrap();
+
+        let today = Local::now().date_naive();
+        assert_eq!(
+            storage.removal_log,
+            vec![(today, 1, 2), (today, 1, 1), (today, 2, 3)]
+        );
+        assert_eq!(storage.removal_summary(None), vec![(1, 3), (2, 3)]);
+        assert!(storage.removal_summary(today.succ_opt()).is_empty());
+    }
+
+    #[test]
+    fn test_removing_more_than_stocked_logs_the_stock() {
+        let mut storage = test_storage();
+        storage.new_product("milk".to_string(), 250).unwrap();
+        storage.restock_product(1, 4, None).unwrap();
+
+        storage.remove_stock(1, 10).unwrap();
+
+        let today = Local::now().date_naive();
+        assert_eq!(storage.removal_log, vec![(today, 1, 4)]);
+        assert_eq!(storage.product_list.get(1).unwrap().quantity, 0);
+        assert!(!storage.warehouse.contains_product(1));
+    }
+
+    #[test]
+    fn test_removing_undated_stock_logs_every_item_removed() {
+        let mut storage = test_storage();
+        storage.new_product("milk".to_string(), 250).unwrap();
+        storage.restock_product(1, 5, None).unwrap();
+
+        storage.remove_stock(1, 1).unwrap();
+
+        // None of the items are dated, so the warehouse clears all of them.
+        let today = Local::now().date_naive();
+        assert!(!storage.warehouse.contains_product(1));
+        assert_eq!(storage.product_list.get(1).unwrap().quantity, 0);
+        assert_eq!(storage.removal_log, vec![(today, 1, 5)]);
+    }
+
+    #[test]
+    fn test_reorder_suggestions() {
+        let mut storage = Storage::new("test".to_string(), None);
+        storage.warehouse.initialize_rows(1, 2, 5);
+        for name in ["milk", "eggs", "bread"] {
+            storage.new_product(name.to_string(), 100).unwrap();
+        }
+        let date = NaiveDate::from_ymd_opt(2025, 1, 1);
+        storage.restock_product(1, 6, date).unwrap();
+        storage.restock_product(2, 2, date).unwrap();
+        storage.remove_stock(1, 3).unwrap();
+        storage.set_reorder_level(1, Some(3)).unwrap();
+        storage.set_reorder_level(2, Some(1)).unwrap();
+        storage.set_reorder_level(3, Some(4)).unwrap();
+
+        assert_eq!(storage.check_available_space(), 5);
+        assert_eq!(storage.reorder_suggestions(), vec![(1, 3), (3, 2)]);
+    }
+
+    #[test]
+    fn test_update_product_is_atomic() {
+        let mut storage = test_storage();
+        storage.new_product("apple".to_string(), 100).unwrap();
+        storage.new_product("pear".to_string(), 200).unwrap();
+
+        let result = storage.update_product(1, Some("banana".to_string()), Some(2));
+        assert!(matches!(result, Err(ProductError(ProductError::IDExists))));
+        assert_eq!(storage.get_product_by_id(1), Some("apple"));
+        assert_eq!(storage.get_product_by_id(2), Some("pear"));
+        assert_eq!(storage.find_product_id("banana"), None);
+    }
+
+    #[test]
+    fn test_retag_items() {
+        let mut storage = test_storage();
+        storage.new_product("milk".to_string(), 250).unwrap();
+        storage.new_product("cream".to_string(), 400).unwrap();
+        let date = NaiveDate::from_ymd_opt(2025, 1, 1);
+        storage.restock_product(1, 3, date).unwrap();
+        storage.restock_product(2, 1, date).unwrap();
+        let milk_positions = storage.warehouse.find_all_item_occurences(1);
+
+        assert_eq!(storage.retag_items(1, 2, 2).unwrap(), 2);
+        assert_eq!(storage.product_list.products[&1].quantity, 1);
+        assert_eq!(storage.product_list.products[&2].quantity, 3);
+        let ids: Vec<u32> = milk_positions
+            .iter()
+            .map(|&(r, c, z)| storage.warehouse.get_item(r, c, z).unwrap().id)
+            .collect();
+        assert_eq!(ids, vec![2, 2, 1]);
+        assert_eq!(storage.warehouse.find_all_item_occurences(2).len(), 3);
+        assert!(matches!(storage.retag_items(1, 9, 1), Err(ProductNotFound)));
+    }
+
+    #[test]
+    fn test_quiet_restock_logs_nothing() {
+        let mut storage = test_storage();
+        let capture = Rc::new(RefCell::new(CaptureLogger::default()));
+        storage.warehouse.log = Log::new(capture.clone());
+        storage.new_product("milk".to_string(), 250).unwrap();
+        capture.borrow_mut().messages.clear();
+
+        storage.set_quiet(true);
+        storage.restock_product(1, 2, None).unwrap();
+        assert!(capture.borrow().messages.is_empty());
+
+        storage.set_quiet(false);
+        storage.restock_product(1, 2, None).unwrap();
+        assert!(!capture.borrow().messages.is_empty());
+        assert_eq!(storage.product_list.products[&1].quantity, 4);
+    }
+
+    #[test]
+    fn test_log_captures_product_added() {
+        let mut storage = test_storage();
+        let capture = Rc::new(RefCell::new(CaptureLogger::default()));
+        storage.warehouse.log = Log::new(capture.clone());
+
+        storage.new_product("milk".to_string(), 250).unwrap();
+        storage.new_product("eggs".to_string(), 300).unwrap();
+        assert_eq!(
+            capture.borrow().messages,
+            vec!["Product 1 added", "Product 2 added"]
+        );
+        assert!(capture.borrow().errors.is_empty());
+    }
+
+    #[test]
+    fn test_shelf_life() {
+        let mut storage = test_storage();
+        storage.new_product("milk".to_string(), 250).unwrap();
+        storage.new_product("salt".to_string(), 100).unwrap();
+        storage
+            .restock_product(1, 1, NaiveDate::from_ymd_opt(2025, 1, 11))
+            .unwrap();
+        storage
+            .restock_product(1, 1, NaiveDate::from_ymd_opt(2024, 12, 30))
+            .unwrap();
+        storage.restock_product(2, 3, None).unwrap();
+
+        let today = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        assert_eq!(storage.shelf_life(1, today), Some((-2, 10, 4.0)));
+        assert_eq!(storage.shelf_life(2, today), None);
+        assert_eq!(storage.shelf_life(9, today), None);
+    }
+
+    #[test]
+    fn test_footprint() {
+        let mut storage = test_storage();
+        storage.new_product("milk".to_string(), 250).unwrap();
+        storage.new_product("eggs".to_string(), 300).unwrap();
+        storage.new_product("salt".to_string(), 100).unwrap();
+        storage.restock_product(1, 4, None).unwrap();
+        storage.restock_product(2, 2, None).unwrap();
+        storage
+            .warehouse
+            .add_item(2, 1, 3, ProductItem::new(2, 2, 1, 3, None))
+            .unwrap();
+
+        assert_eq!(storage.warehouse.flat_map(), "11111100000010000000");
+        assert_eq!(
+            storage.footprint(),
+            vec![(1, 4, 20.0), (2, 3, 15.0), (3, 0, 0.0)]
+        );
+        assert_eq!(storage.warehouse.largest_free_block(), 7);
+    }
+}
@@ -0,0 +1,81 @@
+use std::{
+    cell::RefCell,
+    fmt::{self, Debug, Formatter},
+    rc::Rc,
+};
+
+// Destination for status and error messages, so they can be redirected
+// (e.g. captured in tests) instead of always going to stdout/stderr.
+pub trait Logger {
+    fn info(&mut self, message: &str);
+    fn error(&mut self, message: &str);
+}
+
+// Status messages on stdout, errors on stderr.
+pub struct StdLogger;
+
+impl Logger for StdLogger {
+    fn info(&mut self, message: &str) {
+        println!("{}", message);
+    }
+
+    fn error(&mut self, message: &str) {
+        eprintln!("{}", message);
+    }
+}
+
+// Keeps every message in memory so tests can assert on them.
+#[derive(Debug, Default)]
+pub struct CaptureLogger {
+    pub messages: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+impl Logger for CaptureLogger {
+    fn info(&mut self, message: &str) {
+        self.messages.push(message.to_string());
+    }
+
+    fn error(&mut self, message: &str) {
+        self.errors.push(message.to_string());
+    }
+}
+
+// Handle held by the warehouse and storage. Quiet mode drops status messages
+// but still lets errors through.
+#[derive(Clone)]
+pub struct Log {
+    pub quiet: bool,
+    logger: Rc<RefCell<dyn Logger>>,
+}
+
+impl Log {
+    pub fn new(logger: Rc<RefCell<dyn Logger>>) -> Self {
+        Log {
+            quiet: false,
+            logger,
+        }
+    }
+
+    pub fn info(&self, message: String) {
+        if !self.quiet {
+            self.logger.borrow_mut().info(&message);
+        }
+    }
+
+    pub fn error(&self, message: String) {
+        self.logger.borrow_mut().error(&message);
+    }
+}
+
+impl Default for Log {
+    fn default() -> Self {
+        Log::new(Rc::new(RefCell::new(StdLogger)))
+    }
+}
+
+impl Debug for Log {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("Log").field("quiet", &self.quiet).finish()
+    }
+}
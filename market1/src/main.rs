@@ -1,4 +1,5 @@
 pub mod inventory;
+pub mod log;
 pub mod product;
 pub mod repl;
 pub mod warehouse;
@@ -10,5 +11,6 @@ fn main() {
     let args: Vec<String> = args().collect();
     if let Err(e) = run(args) {
         eprintln!("Error: {}", e);
+        std::process::exit(e.exit_code());
     }
 }
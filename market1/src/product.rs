@@ -14,6 +14,8 @@ pub struct Product {
     pub name: String,
     pub price: u64,
     pub quantity: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reorder_level: Option<usize>,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -45,6 +47,49 @@ impl Display for Product {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    Id,
+    Quantity,
+    Value,
+    Name,
+    Price,
+}
+
+impl SortKey {
+    pub fn parse(key: &str) -> Option<SortKey> {
+        match key.to_lowercase().as_str() {
+            "id" => Some(SortKey::Id),
+            "quantity" | "qty" => Some(SortKey::Quantity),
+            "value" => Some(SortKey::Value),
+            "name" => Some(SortKey::Name),
+            "price" => Some(SortKey::Price),
+            _ => None,
+        }
+    }
+}
+
+fn write_table(f: &mut impl fmt::Write, products: &[&Product]) -> fmt::Result {
+    writeln!(f, "{:<6}{:<20}{:>12}{:>10}", "ID", "Name", "Price", "Quantity")?;
+    for product in products {
+        writeln!(
+            f,
+            "{:<6}{:<20}{:>12}{:>10}",
+            product.id,
+            product.name,
+            format_price(product.price),
+            product.quantity
+        )?;
+    }
+    Ok(())
+}
+
+impl Display for ProductList {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write_table(f, &self.sorted_by(SortKey::Id))
+    }
+}
+
 #[derive(Debug)]
 pub enum ErrorKind {
     NotEnoughQuantity,
@@ -88,7 +133,9 @@ impl Error for ErrorKind {}
 
 
 
-fn format_price(price: u64) -> String {
+// Formats cents as dollars, e.g. 1999 as "$19.99". Parsing the result with
+// the repl's price parser gives back the same cents for every u64.
+pub(crate) fn format_price(price: u64) -> String {
     let numeral = price / 100;
     let decimal = price % 100;
 
@@ -103,6 +150,7 @@ impl Product {
             name,
             price,
             quantity,
+            reorder_level: None,
         }
     }
 
@@ -124,6 +172,22 @@ impl Product {
         self.price = price;
     }
 
+    pub fn set_reorder_level(&mut self, level: Option<usize>) {
+        self.reorder_level = level;
+    }
+
+    pub fn needs_reorder(&self) -> bool {
+        match self.reorder_level {
+            Some(level) => self.quantity <= level,
+            None => false,
+        }
+    }
+
+    // Price of the whole stock, in cents.
+    pub fn value(&self) -> u128 {
+        self.price as u128 * self.quantity as u128
+    }
+
     pub fn print_price(&self) {
         println!("Price: {}", format_price(self.price));
     }
@@ -203,7 +267,6 @@ impl ProductList {
         if self.products.values().any(|p| p.name == product.name) {
             return Err(NameExists);
         }
-        println!("Product {} added", id);
         self.products.insert(product.id, product);
         Ok(())
     }
@@ -237,9 +300,30 @@ impl ProductList {
     }
 
     pub fn list(&self) {
-        for product in self.products.values() {
-            println!("{}", product);
-        }
+        print!("{}", self);
+    }
+
+    // Quantity and value sort from highest to lowest, everything else from
+    // lowest to highest. Ties are broken by id.
+    pub fn sorted_by(&self, key: SortKey) -> Vec<&Product> {
+        let mut products: Vec<&Product> = self.products.values().collect();
+        products.sort_by(|a, b| {
+            let ordering = match key {
+                SortKey::Id => a.id.cmp(&b.id),
+                SortKey::Quantity => b.quantity.cmp(&a.quantity),
+                SortKey::Value => b.value().cmp(&a.value()),
+                SortKey::Name => a.name.cmp(&b.name),
+                SortKey::Price => a.price.cmp(&b.price),
+            };
+            ordering.then(a.id.cmp(&b.id))
+        });
+        products
+    }
+
+    pub fn table_by(&self, key: SortKey) -> String {
+        let mut table = String::new();
+        write_table(&mut table, &self.sorted_by(key)).unwrap_or_default();
+        table
     }
 }
 
@@ -248,3 +332,58 @@ impl Default for ProductList {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_product_list_display_is_sorted_by_id() {
+        let mut list = ProductList::new();
+        list.add(Product::new(2, "pear".to_string(), 1999, 4)).unwrap();
+        list.add(Product::new(1, "apple".to_string(), 250, 10)).unwrap();
+
+        let expected = "\
+ID    Name                       Price  Quantity
+1     apple                      $2.50        10
+2     pear                      $19.99         4
+";
+        assert_eq!(list.to_string(), expected);
+    }
+
+    fn ids(products: Vec<&Product>) -> Vec<u32> {
+        products.iter().map(|product| product.id).collect()
+    }
+
+    fn sample_list() -> ProductList {
+        let mut list = ProductList::new();
+        list.add(Product::new(1, "milk".to_string(), 250, 4)).unwrap();
+        list.add(Product::new(2, "bread".to_string(), 100, 10)).unwrap();
+        list.add(Product::new(3, "apple".to_string(), 50, 30)).unwrap();
+        list.add(Product::new(4, "cheese".to_string(), 1000, 0)).unwrap();
+        list
+    }
+
+    #[test]
+    fn test_sorted_by_value() {
+        let list = sample_list();
+        assert_eq!(ids(list.sorted_by(SortKey::Value)), vec![3, 1, 2, 4]);
+        assert_eq!(ids(list.sorted_by(SortKey::Quantity)), vec![3, 2, 1, 4]);
+        assert_eq!(ids(list.sorted_by(SortKey::Price)), vec![3, 2, 1, 4]);
+    }
+
+    #[test]
+    fn test_sorted_by_name() {
+        let list = sample_list();
+        assert_eq!(ids(list.sorted_by(SortKey::Name)), vec![3, 2, 4, 1]);
+        let table = list.table_by(SortKey::Name);
+        let names: Vec<&str> = table
+            .lines()
+            .skip(1)
+            .filter_map(|line| line.split_whitespace().nth(1))
+            .collect();
+        assert_eq!(names, vec!["apple", "bread", "cheese", "milk"]);
+        assert_eq!(SortKey::parse("Value"), Some(SortKey::Value));
+        assert_eq!(SortKey::parse("weight"), None);
+    }
+}
@@ -1,14 +1,15 @@
 use {
     crate::{
         inventory::{ErrorKind as InventoryError, Storage},
-        product::ProductList,
+        product::{ProductList, SortKey},
         warehouse::Warehouse,
     },
     chrono::NaiveDate,
     std::{
         error::Error,
         fmt::{self, Display, Formatter},
-        io::{stdin, stdout, Write},
+        fs,
+        io::{stdin, stdout, BufRead, Write},
         path::Path,
     },
     // clap::{Parser as CliParser,Subcommand},
@@ -44,6 +45,18 @@ pub enum Usage {
     RestockProduct,
     RemoveStock,
     EmptyStock,
+    UpdateProduct,
+    FindSpace,
+    DescribeProduct,
+    Removals,
+    SetReorderLevel,
+    Segregate,
+    BulkRestock,
+    ExpiredZones,
+    RetagItems,
+    ShelfLife,
+    ListProducts,
+    ExportLayout,
     Storage,
 }
 
@@ -66,6 +79,18 @@ impl ErrorKind {
         }
     }
 
+    // I/O failures exit with 1, every other failed command with 2.
+    pub(crate) fn exit_code(&self) -> i32 {
+        match self {
+            InvalidFile
+            | CouldNotSaveStorage
+            | CouldNotCreateStorage
+            | CouldNotLoadStorage
+            | StorageError(InventoryError::Io(_)) => 1,
+            _ => 2,
+        }
+    }
+
     pub(crate) fn detailed_msg(&self) -> String {
         match self {
             StorageError(e) => format!("{}", e),
@@ -85,7 +110,19 @@ impl Usage {
             RestockProduct => "restock_product [id or name] [quantity] [expiration_date]",
             RemoveStock => "remove_stock [id or name] [quantity]",
             EmptyStock => "empty_stock [id or name]",
-            Storage => "storage [create | load <file_path>]",
+            UpdateProduct => "update_product <id or name> [--name <name>] [--id <new_id>]",
+            FindSpace => "find_space <quantity>",
+            DescribeProduct => "describe_product <id or name>",
+            Removals => "removals [since <date>]",
+            SetReorderLevel => "set_reorder_level <id or name> <level | none>",
+            Segregate => "segregate_product <id or name>",
+            BulkRestock => "bulk_restock <file_path>",
+            ExpiredZones => "expired_zones <date>",
+            RetagItems => "retag_items <from id or name> <to id or name> <quantity>",
+            ShelfLife => "shelf_life <date>",
+            ListProducts => "list_products [--by <quantity | value | name | price>]",
+            ExportLayout => "export_layout <file_path>",
+            Storage => "storage [--quiet] [create | load <file_path>]",
         }
     }
 }
@@ -121,16 +158,34 @@ fn read_number() -> Result<u32, ErrorKind> {
 
 #[allow(dead_code)]
 impl Parser {
+    // Parses dollars with up to two decimal places, separated by either a dot
+    // or a comma, into cents. A leading '$' is accepted so that anything
+    // format_price produces parses back to the same number of cents.
     fn price(price: &str) -> Result<u64, ErrorKind> {
-        let normalized_price = price.replace(",", ".");
-
-        match normalized_price.parse::<f64>() {
-            Ok(parsed_price) => {
-                let price_in_cents = (parsed_price * 100.0).round() as u64;
-                Ok(price_in_cents)
-            }
-            Err(_) => Err(InvalidPrice),
+        let normalized_price = price.strip_prefix('$').unwrap_or(price).replace(",", ".");
+        let (dollars, cents) = match normalized_price.split_once('.') {
+            Some((dollars, cents)) => (dollars, cents),
+            None => (normalized_price.as_str(), ""),
+        };
+        let digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+        if (dollars.is_empty() && cents.is_empty()) || !digits(dollars) || !digits(cents) {
+            return Err(InvalidPrice);
         }
+
+        let dollars = match dollars {
+            "" => 0,
+            dollars => dollars.parse::<u64>().map_err(|_| InvalidPrice)?,
+        };
+        // Extra decimal places round to the nearest cent.
+        let mut fraction = cents.bytes().map(|b| (b - b'0') as u64);
+        let tens = fraction.next().unwrap_or(0);
+        let units = fraction.next().unwrap_or(0);
+        let round_up = fraction.next().is_some_and(|digit| digit >= 5) as u64;
+
+        dollars
+            .checked_mul(100)
+            .and_then(|price| price.checked_add(tens * 10 + units + round_up))
+            .ok_or(InvalidPrice)
     }
 
     fn optional_date(date_str: &str) -> Option<NaiveDate> {
@@ -271,7 +326,7 @@ impl Prompt {
     fn storage_load(storage: &mut Storage) -> Result<&mut Storage, ErrorKind> {
         match Prompt::file_path() {
             Some(file_path) => {
-                let default_path_name = format!("./storage-{}.json", &file_path);
+                let default_path_name = Storage::default_file_path(&file_path);
                 let default_path = Path::new(&default_path_name);
                 if !default_path.exists() {
                     match Storage::load(&file_path, storage) {
@@ -298,11 +353,12 @@ impl Prompt {
         let file_path = Prompt::file_path();
 
         let warehouse = Warehouse::new();
-        storage.file_path = file_path.unwrap_or(format!("./storage-{}.json", name));
+        storage.file_path = file_path.unwrap_or(Storage::default_file_path(&name));
         storage.name = name;
         storage.product_list = ProductList::new();
         match Prompt::warehouse_creation(warehouse) {
-            Ok(warehouse) => {
+            Ok(mut warehouse) => {
+                warehouse.log = std::mem::take(&mut storage.warehouse.log);
                 storage.warehouse = warehouse;
                 Ok(storage)
             }
@@ -467,43 +523,28 @@ fn delete_product(storage: &mut Storage, args: &[String]) -> Result<(), ErrorKin
 
 fn restock_product(storage: &mut Storage, args: &[String]) -> Result<(), ErrorKind> {
     match args.len() {
-        2 => match args[0].parse::<u32>() {
-            Ok(id) => match args[1].parse::<usize>() {
-                Ok(quantity) => match storage.restock_product(id, quantity, None) {
-                    Ok(_) => Ok(()),
-                    Err(e) => Err(StorageError(e)),
+        2 | 3 => {
+            let expiry = match args.get(2) {
+                Some(date) => match Parser::optional_date(date) {
+                    Some(expiry) => Some(expiry),
+                    None => return Err(InvalidDate),
                 },
-                Err(_) => Err(InvalidQuantity),
-            },
-            Err(_) => match storage.restock_by_name(&args[0], 1, None) {
-                Ok(_) => Ok(()),
-                Err(e) => Err(StorageError(e)),
-            },
-        },
-        3 => match args[0].parse::<u32>() {
-            Ok(id) => match args[1].parse::<usize>() {
-                Ok(quantity) => match Parser::optional_date(&args[2]) {
-                    Some(expiry) => match storage.restock_product(id, quantity, Some(expiry)) {
+                None => None,
+            };
+            match args[1].parse::<usize>() {
+                Ok(quantity) => match args[0].parse::<u32>() {
+                    Ok(id) => match storage.restock_product(id, quantity, expiry) {
+                        Ok(_) => Ok(()),
+                        Err(e) => Err(StorageError(e)),
+                    },
+                    Err(_) => match storage.restock_by_name(&args[0], quantity, expiry) {
                         Ok(_) => Ok(()),
                         Err(e) => Err(StorageError(e)),
                     },
-                    None => Err(InvalidDate),
-                },
-                Err(_) => Err(InvalidQuantity),
-            },
-            Err(_) => match args[2].parse::<usize>() {
-                Ok(quantity) => match Parser::optional_date(&args[1]) {
-                    Some(expiry) => {
-                        match storage.restock_by_name(&args[0], quantity, Some(expiry)) {
-                            Ok(_) => Ok(()),
-                            Err(e) => Err(StorageError(e)),
-                        }
-                    }
-                    None => Err(InvalidDate),
                 },
                 Err(_) => Err(InvalidQuantity),
-            },
-        },
+            }
+        }
         0 => match Prompt::restock_product(storage) {
             Ok(_) => Ok(()),
             Err(e) => Err(e),
@@ -512,6 +553,47 @@ fn restock_product(storage: &mut Storage, args: &[String]) -> Result<(), ErrorKi
     }
 }
 
+type LineResult = (usize, Result<(), ErrorKind>);
+
+// Restocks every non-empty line of the file as `<id or name> <qty> [expiry]`,
+// returning each line number with its outcome instead of stopping at errors.
+fn bulk_restock_from_file(
+    storage: &mut Storage,
+    path: &str,
+) -> Result<Vec<LineResult>, ErrorKind> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(_) => return Err(InvalidFile),
+    };
+    let mut results = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let args: Vec<String> = line.split_whitespace().map(String::from).collect();
+        if args.is_empty() {
+            continue;
+        }
+        results.push((index + 1, restock_product(storage, &args)));
+    }
+    Ok(results)
+}
+
+fn bulk_restock(storage: &mut Storage, args: &[String]) -> Result<(), ErrorKind> {
+    match args.len() {
+        1 => match bulk_restock_from_file(storage, &args[0]) {
+            Ok(results) => {
+                for (line, result) in results {
+                    match result {
+                        Ok(_) => println!("Line {}: restocked", line),
+                        Err(e) => println!("Line {}: {}", line, e),
+                    }
+                }
+                Ok(())
+            }
+            Err(e) => Err(e),
+        },
+        _ => Err(InvalidArguments(Usage::BulkRestock)),
+    }
+}
+
 fn remove_stock(storage: &mut Storage, args: &[String]) -> Result<(), ErrorKind> {
     match args.len() {
         1 => match args[0].parse::<u32>() {
@@ -568,6 +650,305 @@ fn empty_stock(storage: &mut Storage, args: &[String]) -> Result<(), ErrorKind>
     }
 }
 
+fn update_product(storage: &mut Storage, args: &[String]) -> Result<(), ErrorKind> {
+    if args.is_empty() {
+        return Err(InvalidArguments(Usage::UpdateProduct));
+    }
+    let id = match args[0].parse::<u32>() {
+        Ok(id) => id,
+        Err(_) => match storage.find_product_id(&args[0]) {
+            Some(id) => id,
+            None => return Err(StorageError(InventoryError::ProductNotFound)),
+        },
+    };
+
+    let mut new_name = None;
+    let mut new_id = None;
+    let mut flags = args[1..].iter();
+    while let Some(flag) = flags.next() {
+        match (flag.as_str(), flags.next()) {
+            ("--name", Some(name)) => new_name = Some(name.to_string()),
+            ("--id", Some(value)) => match value.parse::<u32>() {
+                Ok(value) => new_id = Some(value),
+                Err(_) => return Err(InvalidId),
+            },
+            _ => return Err(InvalidArguments(Usage::UpdateProduct)),
+        }
+    }
+    if new_name.is_none() && new_id.is_none() {
+        return Err(InvalidArguments(Usage::UpdateProduct));
+    }
+
+    match storage.update_product(id, new_name, new_id) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(StorageError(e)),
+    }
+}
+
+fn find_space(storage: &Storage, args: &[String]) -> Result<(), ErrorKind> {
+    match args.len() {
+        1 => match args[0].parse::<usize>() {
+            Ok(quantity) if quantity > 0 => {
+                match storage.warehouse.find_contiguous_space(quantity) {
+                    Ok(position) => {
+                        println!(
+                            "{} units fit contiguously starting at zone {:?}",
+                            quantity, position
+                        );
+                        Ok(())
+                    }
+                    Err(e) => Err(StorageError(InventoryError::WarehouseError(e))),
+                }
+            }
+            _ => Err(InvalidQuantity),
+        },
+        _ => Err(InvalidArguments(Usage::FindSpace)),
+    }
+}
+
+fn describe_product(storage: &Storage, args: &[String]) -> Result<(), ErrorKind> {
+    match args.len() {
+        1 => {
+            let description = match args[0].parse::<u32>() {
+                Ok(id) => storage.describe_product(id),
+                Err(_) => storage.describe_product_by_name(&args[0]),
+            };
+            match description {
+                Ok(description) => {
+                    println!("{}", description);
+                    Ok(())
+                }
+                Err(e) => Err(StorageError(e)),
+            }
+        }
+        _ => Err(InvalidArguments(Usage::DescribeProduct)),
+    }
+}
+
+fn removals(storage: &Storage, args: &[String]) -> Result<(), ErrorKind> {
+    let since = match args.len() {
+        0 => None,
+        2 if args[0] == "since" => match Parser::optional_date(&args[1]) {
+            Some(date) => Some(date),
+            None => return Err(InvalidDate),
+        },
+        _ => return Err(InvalidArguments(Usage::Removals)),
+    };
+    let summary = storage.removal_summary(since);
+    if summary.is_empty() {
+        println!("No removals recorded");
+    }
+    for (id, quantity) in summary {
+        match storage.get_product_by_id(id) {
+            Some(name) => println!("Product {} ({}): {} units removed", id, name, quantity),
+            None => println!("Product {}: {} units removed", id, quantity),
+        }
+    }
+    Ok(())
+}
+
+fn set_reorder_level(storage: &mut Storage, args: &[String]) -> Result<(), ErrorKind> {
+    match args.len() {
+        2 => {
+            let id = match args[0].parse::<u32>() {
+                Ok(id) => id,
+                Err(_) => match storage.find_product_id(&args[0]) {
+                    Some(id) => id,
+                    None => return Err(StorageError(InventoryError::ProductNotFound)),
+                },
+            };
+            let level = match args[1].as_str() {
+                "none" => None,
+                level => match level.parse::<usize>() {
+                    Ok(level) => Some(level),
+                    Err(_) => return Err(InvalidQuantity),
+                },
+            };
+            match storage.set_reorder_level(id, level) {
+                Ok(_) => Ok(()),
+                Err(e) => Err(StorageError(e)),
+            }
+        }
+        _ => Err(InvalidArguments(Usage::SetReorderLevel)),
+    }
+}
+
+fn reorder_report(storage: &Storage) {
+    let suggestions = storage.reorder_suggestions();
+    if suggestions.is_empty() {
+        println!("No products at or below their reorder level");
+    }
+    for (id, quantity) in suggestions {
+        if let Some(product) = storage.product_list.get(id) {
+            println!(
+                "Product {} ({}): {} in stock, reorder level {}, suggested restock: {}",
+                id,
+                product.name,
+                product.quantity,
+                product.reorder_level.unwrap_or(0),
+                quantity
+            );
+        }
+    }
+}
+
+fn segregate_product(storage: &mut Storage, args: &[String]) -> Result<(), ErrorKind> {
+    match args.len() {
+        1 => {
+            let id = match args[0].parse::<u32>() {
+                Ok(id) => id,
+                Err(_) => match storage.find_product_id(&args[0]) {
+                    Some(id) => id,
+                    None => return Err(StorageError(InventoryError::ProductNotFound)),
+                },
+            };
+            match storage.segregate_product(id) {
+                Ok(_) => Ok(()),
+                Err(e) => Err(StorageError(e)),
+            }
+        }
+        _ => Err(InvalidArguments(Usage::Segregate)),
+    }
+}
+
+fn retag_items(storage: &mut Storage, args: &[String]) -> Result<(), ErrorKind> {
+    match args.len() {
+        3 => {
+            let mut ids = Vec::new();
+            for arg in &args[..2] {
+                match arg.parse::<u32>() {
+                    Ok(id) => ids.push(id),
+                    Err(_) => match storage.find_product_id(arg) {
+                        Some(id) => ids.push(id),
+                        None => return Err(StorageError(InventoryError::ProductNotFound)),
+                    },
+                }
+            }
+            let quantity = match args[2].parse::<usize>() {
+                Ok(quantity) => quantity,
+                Err(_) => return Err(InvalidQuantity),
+            };
+            match storage.retag_items(ids[0], ids[1], quantity) {
+                Ok(retagged) => {
+                    println!(
+                        "Retagged {} items from product {} to product {}",
+                        retagged, ids[0], ids[1]
+                    );
+                    Ok(())
+                }
+                Err(e) => Err(StorageError(e)),
+            }
+        }
+        _ => Err(InvalidArguments(Usage::RetagItems)),
+    }
+}
+
+fn expired_zones(storage: &Storage, args: &[String]) -> Result<(), ErrorKind> {
+    match args.len() {
+        1 => {
+            let today = match Parser::optional_date(&args[0]) {
+                Some(date) => date,
+                None => return Err(InvalidDate),
+            };
+            let zones = storage.warehouse.expired_zones(today);
+            if zones.is_empty() {
+                println!("No expired items before {}", today);
+            }
+            for zone in zones {
+                println!("{}", zone);
+            }
+            Ok(())
+        }
+        _ => Err(InvalidArguments(Usage::ExpiredZones)),
+    }
+}
+
+fn shelf_life(storage: &Storage, args: &[String]) -> Result<(), ErrorKind> {
+    match args.len() {
+        1 => {
+            let today = match Parser::optional_date(&args[0]) {
+                Some(date) => date,
+                None => return Err(InvalidDate),
+            };
+            let mut ids: Vec<&u32> = storage.product_list.products.keys().collect();
+            ids.sort();
+            for id in ids {
+                if let Some((min, max, average)) = storage.shelf_life(*id, today) {
+                    println!(
+                        "Product {} ({}): min {} days, max {} days, average {:.1} days",
+                        id, storage.product_list.products[id].name, min, max, average
+                    );
+                }
+            }
+            Ok(())
+        }
+        _ => Err(InvalidArguments(Usage::ShelfLife)),
+    }
+}
+
+fn footprint(storage: &Storage) {
+    for (id, zones, percentage) in storage.footprint() {
+        println!(
+            "Product {} ({}): {} zones, {:.1}% of the warehouse",
+            id, storage.product_list.products[&id].name, zones, percentage
+        );
+    }
+    println!(
+        "Largest free block: {} zones",
+        storage.warehouse.largest_free_block()
+    );
+}
+
+fn list_products(storage: &Storage, args: &[String]) -> Result<(), ErrorKind> {
+    match args {
+        [] => storage.list_products(),
+        [flag, key] if flag == "--by" || flag == "by" => match SortKey::parse(key) {
+            Some(key) => storage.list_products_by(key),
+            None => return Err(InvalidArguments(Usage::ListProducts)),
+        },
+        _ => return Err(InvalidArguments(Usage::ListProducts)),
+    }
+    Ok(())
+}
+
+fn export_layout(storage: &Storage, args: &[String]) -> Result<(), ErrorKind> {
+    match args.len() {
+        1 => match fs::write(&args[0], storage.warehouse.layout_csv()) {
+            Ok(_) => {
+                println!("Warehouse layout exported to {}", args[0]);
+                Ok(())
+            }
+            Err(e) => Err(StorageError(InventoryError::Io(e))),
+        },
+        _ => Err(InvalidArguments(Usage::ExportLayout)),
+    }
+}
+
+fn repair(storage: &mut Storage) {
+    let before = storage.warehouse.available_space;
+    storage.warehouse.recompute_counters();
+    println!(
+        "Warehouse counters recomputed, available space: {} -> {}",
+        before, storage.warehouse.available_space
+    );
+}
+
+fn list_storages() -> Result<(), ErrorKind> {
+    match Storage::find_storage_files(Path::new(".")) {
+        Ok(files) if files.is_empty() => {
+            println!("No storages found in the current directory");
+            Ok(())
+        }
+        Ok(files) => {
+            for file in files {
+                println!("{}", file.display());
+            }
+            Ok(())
+        }
+        Err(e) => Err(StorageError(InventoryError::Io(e))),
+    }
+}
+
 fn save_storage(storage: &Storage) -> Result<(), ErrorKind> {
     match storage.save() {
         Ok(_) => Ok(()),
@@ -583,6 +964,20 @@ fn resolve_storage_command(command: &str) -> &str {
         "restock" => "restock_product",
         "remove" => "remove_stock",
         "empty" => "empty_stock",
+        "update" => "update_product",
+        "update-product" => "update_product",
+        "fit" => "find_space",
+        "describe" => "describe_product",
+        "removed" => "removals",
+        "reorder" => "reorder_report",
+        "reorder-report" => "reorder_report",
+        "segregate" => "segregate_product",
+        "bulk-restock" => "bulk_restock",
+        "expired-zones" => "expired_zones",
+        "retag" => "retag_items",
+        "shelf-life" => "shelf_life",
+        "export-layout" => "export_layout",
+        "list" => "list_products",
         "ls" => "list_products",
         _ => command,
     }
@@ -592,30 +987,39 @@ fn resolve_intro_command(command: &str) -> &str {
     match command {
         "create" => "create_storage",
         "load" => "load_storage",
+        "list" => "list_storages",
+        "ls" => "list_storages",
+        "list-storages" => "list_storages",
         _ => command,
     }
 }
 
 fn confirm_exit() -> bool {
     println!("Are you sure you want to exit? (y/n)");
-    let mut input = String::new();
-    stdin().read_line(&mut input).unwrap();
-    input.trim().eq_ignore_ascii_case("y")
+    match read_line(&mut stdin().lock()) {
+        Some(input) => input.trim().eq_ignore_ascii_case("y"),
+        None => false,
+    }
 }
 
-fn intro_repl() -> Result<(), ErrorKind> {
+// `next_line` yields one line of input per call and None once input is
+// exhausted, which ends the loop.
+fn intro_repl(
+    next_line: &mut impl FnMut() -> Option<String>,
+    quiet: bool,
+) -> Result<(), ErrorKind> {
     println!("Welcome to the storage management system");
     loop {
         print!("> ");
         stdout().flush().unwrap();
-        let mut input = String::new();
-        stdin().read_line(&mut input).unwrap();
-
-        let parts = input.trim().split(' ').collect::<Vec<&str>>();
-        let command = parts[0];
+        let (command, _) = match next_line() {
+            Some(line) => read_user_input(&line),
+            None => break,
+        };
         let mut storage = Storage::new("default".to_string(), None);
+        storage.set_quiet(quiet);
 
-        match resolve_intro_command(command) {
+        match resolve_intro_command(&command) {
             "load_storage" => match Prompt::storage_load(&mut storage) {
                 Ok(loaded) => return storage_repl(loaded),
                 Err(e) => {
@@ -630,78 +1034,213 @@ fn intro_repl() -> Result<(), ErrorKind> {
                     continue;
                 }
             },
+            "list_storages" => match list_storages() {
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("{}", e);
+                    continue;
+                }
+            },
+            "help" => print_intro_help(),
             "exit" => break,
+            "" => continue,
             _ => println!("Invalid command"),
         }
     }
     Ok(())
 }
 
-fn read_user_input() -> (String, Vec<String>) {
-    let mut input = String::new();
-    stdin().read_line(&mut input).unwrap();
-    let parts = input.trim().split(' ').collect::<Vec<&str>>();
-    let command = parts[0].to_string();
-    let args = parts[1..].iter().map(|s| s.to_string()).collect();
-    (command, args)
+// None on EOF or when the line could not be read.
+fn read_line(input: &mut impl BufRead) -> Option<String> {
+    let mut line = String::new();
+    match input.read_line(&mut line) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line),
+    }
+}
+
+// Splits a line into its command and arguments. An empty line gives an empty
+// command.
+fn read_user_input(line: &str) -> (String, Vec<String>) {
+    let mut parts = line.split_whitespace().map(|s| s.to_string());
+    let command = parts.next().unwrap_or_default();
+    (command, parts.collect())
+}
+
+// Errors from storage commands go through the storage's logger.
+fn report_error(storage: &Storage, e: ErrorKind) {
+    storage.log().error(e.to_string());
+}
+
+// Prints the zones a command freed or occupied, given the warehouse's flat
+// map from before it ran.
+fn print_occupancy_diff(storage: &Storage, before: &str) {
+    let after = storage.warehouse.flat_map();
+    let changes = storage.warehouse.flat_map_diff(before, &after);
+    if changes.is_empty() {
+        println!("No zones changed");
+    }
+    for (zone, change) in changes {
+        println!("Zone {:?} {}", zone, change.as_str());
+    }
 }
 
 fn storage_repl(storage: &mut Storage) -> Result<(), ErrorKind> {
+    let mut diff_mode = false;
     loop {
         print!("{} > ", storage.name);
         stdout().flush().unwrap();
-        let (command, args) = read_user_input();
+        let (command, args) = match read_line(&mut stdin().lock()) {
+            Some(line) => read_user_input(&line),
+            None => break,
+        };
+        let before = diff_mode.then(|| storage.warehouse.flat_map());
         match resolve_storage_command(&command) {
             "add_product" => match add_product(storage, &args) {
                 Ok(_) => {}
                 Err(e) => {
-                    eprintln!("{}", e);
+                    report_error(storage, e);
                     continue;
                 }
             },
             "delete_product" => match delete_product(storage, &args) {
                 Ok(_) => {}
                 Err(e) => {
-                    eprintln!("{}", e);
+                    report_error(storage, e);
                     continue;
                 }
             },
             "restock_product" => match restock_product(storage, &args) {
                 Ok(_) => {}
                 Err(e) => {
-                    eprintln!("{}", e);
+                    report_error(storage, e);
                     continue;
                 }
             },
             "remove_stock" => match remove_stock(storage, &args) {
                 Ok(_) => {}
                 Err(e) => {
-                    eprintln!("{}", e);
+                    report_error(storage, e);
                     continue;
                 }
             },
             "empty_stock" => match empty_stock(storage, &args) {
                 Ok(_) => {}
                 Err(e) => {
-                    eprintln!("{}", e);
+                    report_error(storage, e);
+                    continue;
+                }
+            },
+            "update_product" => match update_product(storage, &args) {
+                Ok(_) => {}
+                Err(e) => {
+                    report_error(storage, e);
+                    continue;
+                }
+            },
+            "find_space" => match find_space(storage, &args) {
+                Ok(_) => {}
+                Err(e) => {
+                    report_error(storage, e);
+                    continue;
+                }
+            },
+            "describe_product" => match describe_product(storage, &args) {
+                Ok(_) => {}
+                Err(e) => {
+                    report_error(storage, e);
+                    continue;
+                }
+            },
+            "removals" => match removals(storage, &args) {
+                Ok(_) => {}
+                Err(e) => {
+                    report_error(storage, e);
+                    continue;
+                }
+            },
+            "set_reorder_level" => match set_reorder_level(storage, &args) {
+                Ok(_) => {}
+                Err(e) => {
+                    report_error(storage, e);
+                    continue;
+                }
+            },
+            "reorder_report" => reorder_report(storage),
+            "segregate_product" => match segregate_product(storage, &args) {
+                Ok(_) => {}
+                Err(e) => {
+                    report_error(storage, e);
+                    continue;
+                }
+            },
+            "bulk_restock" => match bulk_restock(storage, &args) {
+                Ok(_) => {}
+                Err(e) => {
+                    report_error(storage, e);
                     continue;
                 }
             },
+            "retag_items" => match retag_items(storage, &args) {
+                Ok(_) => {}
+                Err(e) => {
+                    report_error(storage, e);
+                    continue;
+                }
+            },
+            "shelf_life" => match shelf_life(storage, &args) {
+                Ok(_) => {}
+                Err(e) => {
+                    report_error(storage, e);
+                    continue;
+                }
+            },
+            "expired_zones" => match expired_zones(storage, &args) {
+                Ok(_) => {}
+                Err(e) => {
+                    report_error(storage, e);
+                    continue;
+                }
+            },
+            "footprint" => footprint(storage),
+            "export_layout" => match export_layout(storage, &args) {
+                Ok(_) => {}
+                Err(e) => {
+                    report_error(storage, e);
+                    continue;
+                }
+            },
+            "repair" => repair(storage),
+            "diff" => {
+                diff_mode = !diff_mode;
+                match diff_mode {
+                    true => println!("Occupancy diff on"),
+                    false => println!("Occupancy diff off"),
+                }
+                continue;
+            }
             "save" => match save_storage(storage) {
                 Ok(_) => {}
                 Err(e) => {
-                    eprintln!("{}", e);
+                    report_error(storage, e);
+                    continue;
+                }
+            },
+            "list_products" => match list_products(storage, &args) {
+                Ok(_) => {}
+                Err(e) => {
+                    report_error(storage, e);
                     continue;
                 }
             },
-            "list_products" => storage.list_products(),
             "help" => print_storage_help(),
+            "" => {}
             "exit" => {
                 if confirm_exit() {
                     match save_storage(storage) {
                         Ok(_) => break,
                         Err(e) => {
-                            eprintln!("{}", e);
+                            report_error(storage, e);
                             continue;
                         }
                     }
@@ -714,6 +1253,9 @@ fn storage_repl(storage: &mut Storage) -> Result<(), ErrorKind> {
             }
             _ => println!("Invalid command"),
         }
+        if let Some(before) = before {
+            print_occupancy_diff(storage, &before);
+        }
     }
     Ok(())
 }
@@ -725,17 +1267,32 @@ fn print_storage_help() {
     println!("  restock_product <id> <quantity> [expiration_date]");
     println!("  remove_stock <id> [quantity]");
     println!("  empty_stock <id>");
-    println!("  list_products");
+    println!("  update_product <id> [--name <name>] [--id <new_id>]");
+    println!("  find_space <quantity>");
+    println!("  describe_product <id or name>");
+    println!("  removals [since <date>]");
+    println!("  set_reorder_level <id or name> <level | none>");
+    println!("  reorder_report");
+    println!("  segregate_product <id or name>");
+    println!("  bulk_restock <file_path> (one <id or name> <quantity> [expiration_date] per line)");
+    println!("  retag_items <from id or name> <to id or name> <quantity>");
+    println!("  shelf_life <date> (days until expiry of each product's dated stock)");
+    println!("  expired_zones <date> (zones holding items that expired before the date)");
+    println!("  footprint (zones used by each product and the largest free block)");
+    println!("  export_layout <file_path> (CSV of the product id in every zone)");
+    println!("  repair (recompute warehouse space counters)");
+    println!("  diff (toggle printing the zones each command frees or occupies)");
+    println!("  list_products [--by <quantity | value | name | price>]");
     println!("  save");
     println!("  exit (save and exit)");
     println!("  force_exit (exit without saving)");
 }
 
-#[allow(dead_code)]
 fn print_intro_help() {
     println!("Commands:");
     println!("  create_storage");
     println!("  load_storage");
+    println!("  list_storages");
     println!("  exit");
 }
 
@@ -754,22 +1311,18 @@ fn resolve_arg(arg: &str) -> &str {
 }
 
 pub fn run(args: Vec<String>) -> Result<(), ErrorKind> {
+    let quiet = args.iter().any(|arg| arg == "--quiet");
+    let args: Vec<String> = args.into_iter().filter(|arg| arg != "--quiet").collect();
     let mut storage = Storage::new("default".to_string(), None);
+    storage.set_quiet(quiet);
 
     if args.len() > 1 {
         let command = &args[1];
-        let load = {
-            let path: &str = &args[2];
-            match Storage::load(path, &mut storage) {
-                Ok(loaded) => Ok(loaded),
-                Err(_) => Err(CouldNotLoadStorage),
-            }
-        };
         match resolve_arg(command.as_str()) {
-            "load_storage" => match args.len() {
-                3 => match load {
+            "load_storage" => match args.get(2) {
+                Some(path) if args.len() == 3 => match Storage::load(path, &mut storage) {
                     Ok(_) => Ok(()),
-                    Err(e) => Err(e),
+                    Err(_) => Err(CouldNotLoadStorage),
                 },
                 _ => Err(InvalidArguments(Usage::Storage)),
             },
@@ -783,9 +1336,125 @@ pub fn run(args: Vec<String>) -> Result<(), ErrorKind> {
             _ => Err(InvalidCommand),
         }
     } else {
-        match intro_repl() {
+        match intro_repl(&mut || read_line(&mut stdin().lock()), quiet) {
             Ok(_) => Ok(()),
             Err(e) => Err(e),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        log::{CaptureLogger, Log},
+        product::format_price,
+    };
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn test_bulk_restock_continues_past_errors() {
+        let mut storage = Storage::new("test".to_string(), None);
+        storage.warehouse.initialize_rows(1, 2, 5);
+        assert!(storage.new_product("milk".to_string(), 199).is_ok());
+
+        let path = std::env::temp_dir().join(format!("bulk-restock-{}.txt", std::process::id()));
+        fs::write(&path, "milk 3 2030-01-01\nbread 2\n").unwrap();
+        let results = bulk_restock_from_file(&mut storage, path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+
+        let results = match results {
+            Ok(results) => results,
+            Err(e) => panic!("{}", e),
+        };
+        assert_eq!(results.len(), 2);
+        assert!(results[0].1.is_ok());
+        assert!(matches!(
+            results[1],
+            (2, Err(StorageError(InventoryError::ProductNotFound)))
+        ));
+        assert_eq!(storage.product_list.products[&1].quantity, 3);
+    }
+
+    #[test]
+    fn test_errors_are_reported_through_logger() {
+        let mut storage = Storage::new("test".to_string(), None);
+        let capture = Rc::new(RefCell::new(CaptureLogger::default()));
+        storage.warehouse.log = Log::new(capture.clone());
+
+        let args = vec!["ghost".to_string()];
+        if let Err(e) = delete_product(&mut storage, &args) {
+            report_error(&storage, e);
+        }
+        assert_eq!(capture.borrow().errors, vec!["Storage error: Product Not Found"]);
+    }
+
+    #[test]
+    fn test_read_user_input_handles_empty_lines() {
+        assert_eq!(read_user_input(""), (String::new(), vec![]));
+        assert_eq!(read_user_input("   \n"), (String::new(), vec![]));
+        assert_eq!(
+            read_user_input("restock  milk 3\n"),
+            ("restock".to_string(), vec!["milk".to_string(), "3".to_string()])
+        );
+        assert_eq!(read_line(&mut "".as_bytes()), None);
+        assert_eq!(read_line(&mut "\n".as_bytes()), Some("\n".to_string()));
+    }
+
+    #[test]
+    fn test_intro_repl_stops_at_eof() {
+        let mut lines = ["", "  ", "help"].into_iter().map(String::from);
+        assert!(intro_repl(&mut || lines.next(), false).is_ok());
+        assert_eq!(lines.next(), None);
+    }
+
+    #[test]
+    fn test_run_load_without_path() {
+        let args = vec!["prog".to_string(), "load".to_string()];
+        assert!(matches!(run(args), Err(InvalidArguments(Usage::Storage))));
+    }
+
+    #[test]
+    fn test_price_parsing() {
+        assert_eq!(Parser::price("2.5").unwrap(), 250);
+        assert_eq!(Parser::price("2,50").unwrap(), 250);
+        assert_eq!(Parser::price("$19.99").unwrap(), 1999);
+        assert_eq!(Parser::price("3").unwrap(), 300);
+        assert_eq!(Parser::price(".5").unwrap(), 50);
+        assert_eq!(Parser::price("2.999").unwrap(), 300);
+        for invalid in [
+            "",
+            ".",
+            "-1",
+            "1.2.3",
+            "abc",
+            "NaN",
+            "1e3",
+            "$",
+            "184467440737095516.16",
+        ] {
+            assert!(Parser::price(invalid).is_err(), "{:?} parsed", invalid);
+        }
+    }
+
+    #[test]
+    fn test_price_round_trip() {
+        let large = [
+            u64::MAX,
+            u64::MAX - 1,
+            u64::MAX / 100 * 100,
+            1 << 53,
+            (1 << 53) + 1,
+        ];
+        for price in (0..100_000)
+            .chain((0..u64::MAX).step_by(1 << 48))
+            .chain(large)
+        {
+            let formatted = format_price(price);
+            match Parser::price(&formatted) {
+                Ok(parsed) => assert_eq!(parsed, price, "{} parsed back wrong", formatted),
+                Err(e) => panic!("{} did not parse: {}", formatted, e),
+            }
+        }
+    }
+}
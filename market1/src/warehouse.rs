@@ -1,4 +1,4 @@
-use crate::product::ProductItem;
+use crate::{log::Log, product::ProductItem};
 use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 use std::{
@@ -40,6 +40,23 @@ pub struct Warehouse {
     pub capacity: usize,
     pub available_space: usize,
     pub rows: Vec<Row>,
+    #[serde(skip)]
+    pub log: Log,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ZoneChange {
+    Freed,
+    Occupied,
+}
+
+impl ZoneChange {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ZoneChange::Freed => "freed",
+            ZoneChange::Occupied => "occupied",
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -94,8 +111,8 @@ impl Display for Zone {
             self.column_number, self.row_number, self.zone_number
         );
         if let Some(product) = &self.item {
-            if product.expiry_date.is_some() {
-                let date = product.expiry_date.unwrap().to_string();
+            if let Some(expiry_date) = product.expiry_date {
+                let date = expiry_date.to_string();
                 write!(
                     f,
                     "Zone: {}, Product ID: {}, Expiry Date: {}",
@@ -287,6 +304,11 @@ impl Column {
         self.available_space == 0
     }
 
+    pub fn recompute_counters(&mut self) {
+        self.capacity = self.zones.len();
+        self.available_space = self.empty_zones().len();
+    }
+
     pub fn initialize_zones(&mut self, zone_count: usize) {
         for i in 1..=zone_count {
             let zone = Zone::new(i, self.column_number, self.row_number, None);
@@ -355,13 +377,16 @@ impl Column {
     }
 
     pub fn find_item(&self, product_id: u32) -> Option<usize> {
-        self.zones.iter().position(|zone| {
-            if let Some(item) = &zone.item {
-                item.id == product_id
-            } else {
-                false
-            }
-        })
+        self.zones
+            .iter()
+            .find(|zone| {
+                if let Some(item) = &zone.item {
+                    item.id == product_id
+                } else {
+                    false
+                }
+            })
+            .map(|zone| zone.zone_number)
     }
 
     pub fn find_last_item_occurrence_index(&self, product_id: u32) -> Option<usize> {
@@ -377,11 +402,10 @@ impl Column {
     pub fn find_all_item_occurences(&self, product_id: u32) -> Vec<usize> {
         self.zones
             .iter()
-            .enumerate()
-            .filter_map(|(i, zone)| {
+            .filter_map(|zone| {
                 if let Some(item) = &zone.item {
                     if item.id == product_id {
-                        Some(i)
+                        Some(zone.zone_number)
                     } else {
                         None
                     }
@@ -500,6 +524,15 @@ impl Row {
         self.available_space == 0
     }
 
+    pub fn recompute_counters(&mut self) {
+        for column in &mut self.columns {
+            column.recompute_counters();
+        }
+        self.column_count = self.columns.len();
+        self.capacity = self.columns.iter().map(|col| col.capacity).sum();
+        self.available_space = self.columns.iter().map(|col| col.available_space).sum();
+    }
+
     pub fn initialize_columns(&mut self, column_count: usize, zone_per_col: usize) {
         for i in 1..=column_count {
             let mut column = Column::new(i, self.row_number);
@@ -591,7 +624,7 @@ impl Row {
         {
             let col_index = column.column_number;
             if let Some(zone_index) = column.find_last_item_occurrence_index(product_id) {
-                return Some((col_index, zone_index));
+                return Some((col_index, column.zones[zone_index].zone_number));
             }
         }
         None
@@ -599,12 +632,12 @@ impl Row {
 
     pub fn find_all_item_occurences(&self, product_id: u32) -> Vec<(usize, usize)> {
         let mut items = Vec::new();
-        for (col_index, column) in self.columns.iter().enumerate() {
+        for column in &self.columns {
             items.extend(
                 column
                     .find_all_item_occurences(product_id)
                     .iter()
-                    .map(|zone_index| (col_index, *zone_index)),
+                    .map(|zone_number| (column.column_number, *zone_number)),
             );
         }
         items
@@ -620,6 +653,7 @@ impl Warehouse {
             capacity: 0,
             available_space: 0,
             rows: Vec::new(),
+            log: Log::default(),
         }
     }
 
@@ -699,6 +733,69 @@ impl Warehouse {
             .join("")
     }
 
+    // One line per column with its row and column numbers followed by the
+    // product id in each zone, left empty for free zones.
+    pub fn layout_csv(&self) -> String {
+        let zone_count = self
+            .rows
+            .iter()
+            .flat_map(|row| row.columns.iter())
+            .map(|column| column.zones.len())
+            .max()
+            .unwrap_or(0);
+        let mut csv = String::from("row,column");
+        for zone in 1..=zone_count {
+            csv.push_str(&format!(",zone {}", zone));
+        }
+        csv.push('\n');
+
+        for row in &self.rows {
+            for column in &row.columns {
+                csv.push_str(&format!("{},{}", row.row_number, column.column_number));
+                for zone in &column.zones {
+                    csv.push(',');
+                    if let Some(item) = &zone.item {
+                        csv.push_str(&item.id.to_string());
+                    }
+                }
+                csv.push('\n');
+            }
+        }
+        csv
+    }
+
+    // Length of the longest run of empty zones, counted in flat_map order.
+    pub fn largest_free_block(&self) -> usize {
+        self.flat_map()
+            .split('1')
+            .map(|block| block.len())
+            .max()
+            .unwrap_or(0)
+    }
+
+    // Compares two flat maps of this warehouse position by position and
+    // returns each zone whose occupancy changed.
+    pub fn flat_map_diff(
+        &self,
+        before: &str,
+        after: &str,
+    ) -> Vec<((usize, usize, usize), ZoneChange)> {
+        before
+            .chars()
+            .zip(after.chars())
+            .enumerate()
+            .filter(|(_, (old, new))| old != new)
+            .filter_map(|(position, (_, new))| {
+                let change = match new {
+                    '1' => ZoneChange::Occupied,
+                    _ => ZoneChange::Freed,
+                };
+                self.flat_map_position_to_zone(position)
+                    .map(|zone| (zone, change))
+            })
+            .collect()
+    }
+
     pub fn flat_map_position_to_zone(&self, position: usize) -> Option<(usize, usize, usize)> {
         let mut cumulative_capacity = 0;
 
@@ -724,6 +821,35 @@ impl Warehouse {
         None
     }
 
+    pub fn next_position(
+        &self,
+        (r, c, z): (usize, usize, usize),
+    ) -> Option<(usize, usize, usize)> {
+        let row = self.row(r)?;
+        let column = row.column(c)?;
+        if z < column.zones.len() {
+            Some((r, c, z + 1))
+        } else if c < row.columns.len() {
+            Some((r, c + 1, 1))
+        } else if r < self.rows.len() {
+            Some((r + 1, 1, 1))
+        } else {
+            None
+        }
+    }
+
+    // Rebuilds every cached capacity and available_space from the zones
+    // themselves, fixing counters that went out of sync with the contents.
+    pub fn recompute_counters(&mut self) {
+        for row in &mut self.rows {
+            row.recompute_counters();
+        }
+        self.row_count = self.rows.len();
+        self.column_count = self.rows.iter().map(|row| row.column_count).sum();
+        self.capacity = self.rows.iter().map(|row| row.capacity).sum();
+        self.available_space = self.rows.iter().map(|row| row.available_space).sum();
+    }
+
     pub fn initialize_rows(&mut self, row_count: usize, col_per_row: usize, zone_per_col: usize) {
         for i in 1..=row_count {
             let mut row = Row::new(i);
@@ -863,145 +989,96 @@ impl Warehouse {
         true
     }
 
-    pub fn add_items_by_qty(
+    // Number of empty zones from start to the end of the warehouse, start
+    // included.
+    fn free_zones_from(&self, start: (usize, usize, usize)) -> usize {
+        let mut position = Some(start);
+        let mut free = 0;
+        while let Some((r, c, z)) = position {
+            if self.zone(r, c, z).is_some_and(|zone| zone.is_empty()) {
+                free += 1;
+            }
+            position = self.next_position((r, c, z));
+        }
+        free
+    }
+
+    // Moves the product's items into a block with room for qty more and
+    // returns where that block starts.
+    fn make_contiguous_space(
         &mut self,
         product_id: u32,
         qty: usize,
+    ) -> Result<(usize, usize, usize), ErrorKind> {
+        let existing_items = self.find_all_item_occurences(product_id);
+        let total_items = existing_items.len() + qty;
+        let new_contiguous_position = self.find_contiguous_space(total_items)?;
+        let grouped_items = self.group_items_by_expiration(existing_items);
+        self.move_items_to_contiguous_space(grouped_items)?;
+        Ok(new_contiguous_position)
+    }
+
+    // Adds qty items to the empty zones found walking forward from start,
+    // skipping occupied ones, and returns the last zone filled.
+    fn fill_from(
+        &mut self,
+        start: (usize, usize, usize),
+        product_id: u32,
+        qty: usize,
         expiry_date: Option<NaiveDate>,
-    ) -> Result<(), ErrorKind> {
+    ) -> Result<(usize, usize, usize), ErrorKind> {
+        let mut position = Some(start);
+        let mut last_position = start;
         let mut qty_added = 0;
+        while qty_added < qty {
+            let (r, c, z) = position.ok_or(NoContiguousSpace)?;
+            if self.zone(r, c, z).is_some_and(|zone| zone.is_empty()) {
+                self.add_item(r, c, z, ProductItem::new(product_id, r, c, z, expiry_date))?;
+                self.log.info(format!("Added item at {:?}", (r, c, z)));
+                qty_added += 1;
+                last_position = (r, c, z);
+            }
+            position = self.next_position((r, c, z));
+        }
+        Ok(last_position)
+    }
 
+    pub fn add_items_by_qty(
+        &mut self,
+        product_id: u32,
+        qty: usize,
+        expiry_date: Option<NaiveDate>,
+    ) -> Result<(), ErrorKind> {
         if qty > self.available_space {
             return Err(InsufficientSpace);
         }
 
-        if !self.contains_product(product_id) {
-            println!("Product not found in warehouse, adding new items...");
-            match self.find_contiguous_space(qty) {
-                Ok(new_position) => {
-                    println!("Contiguous space at {:?}", new_position);
-                    let first_position = new_position;
-                    let (mut r, mut c, mut z) = &new_position;
-                    while qty_added < qty {
-                        match self.add_item(
-                            r,
-                            c,
-                            z,
-                            ProductItem::new(product_id, r, c, z, expiry_date),
-                        ) {
-                            Ok(_) => {
-                                qty_added += 1;
-                                println!("Added item at {:?}", (r, c, z));
-                                self.available_space -= 1;
-                            }
-                            Err(e) => return Err(e),
-                        }
-                        z += 1;
-                        if z == self.rows[r-1].columns[c-1].zones.len() + 1 {
-                            z = 1;
-                            c += 1;
-                            if c == self.rows[r-1].columns.len() + 1 {
-                                c = 1;
-                                r += 1;
-                            }
-                        }
-                    }
-                    let last_position = (r - 1, c - 1, z - 1);
-                    println!(
-                        "Added {} items from {:?} to {:?}",
-                        qty, first_position, last_position
-                    );
-                    Ok(())
-                }
-                Err(e) => Err(e),
-            }
-        } else if self.is_product_stored_contiguously(product_id) {
-            println!("Product is stored contiguously, adding new items...");
-            let items = self.find_all_item_occurences(product_id);
-            let last_item_position = items.last().unwrap();
-
-            let (mut r, mut c, mut z) = *last_item_position;
-            let first_position = *last_item_position;
-            while qty_added < qty {
-                if let Some(zone) = self.zone_mut(r, c, z) {
-                    if zone.is_empty() {
-                        let new_item = ProductItem::new(product_id, r, c, z, expiry_date);
-                        match zone.add(new_item) {
-                            Ok(_) => {
-                                qty_added += 1;
-                                self.available_space -= 1;
-                            }
-                            Err(e) => return Err(e),
-                        }
-                    }
-
-                    z += 1;
-                    if z == self.rows[r].columns[c].zones.len() + 1 {
-                        z = 1;
-                        c += 1;
-                        if c == self.rows[r].columns.len() + 1 {
-                            c = 1;
-                            r += 1;
-                        }
-                    }
-                }
-            }
-            let last_position = (r - 1, c - 1, z - 1);
-            println!(
-                "Added {} items from {:?} to {:?}",
-                qty, first_position, last_position
-            );
-            Ok(())
+        let first_position = if !self.contains_product(product_id) {
+            self.log.info("Product not found in warehouse, adding new items...".to_string());
+            let new_position = self.find_contiguous_space(qty)?;
+            self.log.info(format!("Contiguous space at {:?}", new_position));
+            new_position
+        } else if self.is_product_stored_contiguously(product_id)
+            && self.free_zones_from(self.find_last_item_occurrence(product_id).unwrap()) >= qty
+        {
+            self.log.info("Product is stored contiguously, adding new items...".to_string());
+            self.find_last_item_occurrence(product_id).unwrap()
         } else {
-            println!("Product is not stored contiguously, moving items and adding new items...");
-            let existing_items = self.find_all_item_occurences(product_id);
-            let total_items = existing_items.len() + qty;
-
-            match self.find_contiguous_space(total_items) {
-                Ok(new_contiguous_position) => {
-                    let grouped_items = self.group_items_by_expiration(existing_items);
-
-                    match self.move_items_to_contiguous_space(grouped_items) {
-                        Ok(_) => {}
-                        Err(e) => return Err(e),
-                    }
-
-                    let (mut r, mut c, mut z) = new_contiguous_position;
-                    let first_position = new_contiguous_position;
-                    while qty_added < qty {
-                        if let Some(zone) = self.zone_mut(r, c, z) {
-                            if zone.is_empty() {
-                                let new_item = ProductItem::new(product_id, r, c, z, expiry_date);
-                                match zone.add(new_item) {
-                                    Ok(_) => {
-                                        qty_added += 1;
-                                        self.available_space -= 1;
-                                    }
-                                    Err(e) => return Err(e),
-                                }
-                            }
-
-                            z += 1;
-                            if z == self.rows[r].columns[c].zones.len() + 1 {
-                                z = 1;
-                                c += 1;
-                                if c == self.rows[r].columns.len() + 1 {
-                                    c = 1;
-                                    r += 1;
-                                }
-                            }
-                        }
-                    }
-                    let last_position = (r - 1, c - 1, z - 1);
-                    println!(
-                        "Added {} items from {:?} to {:?}",
-                        qty, first_position, last_position
-                    );
-                    Ok(())
-                }
-                Err(e) => Err(e),
-            }
-        }
+            // Also reached when the free space after a contiguous product is
+            // too small, so nothing is added before there is room for all of it.
+            self.log.info(
+                "Product is not stored contiguously or has no room after it, moving items..."
+                    .to_string(),
+            );
+            self.make_contiguous_space(product_id, qty)?
+        };
+
+        let last_position = self.fill_from(first_position, product_id, qty, expiry_date)?;
+        self.log.info(format!(
+            "Added {} items from {:?} to {:?}",
+            qty, first_position, last_position
+        ));
+        Ok(())
     }
 
     pub fn move_item(
@@ -1047,7 +1124,10 @@ impl Warehouse {
         }
     }
 
-    pub fn remove_item_by_qty(&mut self, product_id: u32, qty: usize) -> Result<(), ErrorKind> {
+    // Removes the qty items expiring first. When fewer than qty items are
+    // dated, every item of the product is removed instead. Returns how many
+    // items were removed.
+    pub fn remove_item_by_qty(&mut self, product_id: u32, qty: usize) -> Result<usize, ErrorKind> {
         let mut qty_removed = 0;
         let items = self.find_all_item_occurences(product_id);
 
@@ -1059,7 +1139,7 @@ impl Warehouse {
             })
             .collect();
 
-        items_with_dates.sort_by(|a, b| a.1.cmp(&b.1));
+        items_with_dates.sort_by_key(|a| a.1);
 
         if items_with_dates.len() >= qty {
             for ((row, col, zone), _) in items_with_dates.iter().take(qty) {
@@ -1073,11 +1153,11 @@ impl Warehouse {
                     Err(e) => return Err(e),
                 }
             }
-            Ok(())
+            Ok(qty_removed)
         } else {
-            println!("Insufficient quantity, removing all items");
+            self.log.info("Insufficient quantity, removing all items".to_string());
             match self.remove_all_items(product_id) {
-                Ok(_) => Ok(()),
+                Ok(_) => Ok(items.len()),
                 Err(e) => Err(e),
             }
         }
@@ -1097,7 +1177,7 @@ impl Warehouse {
             }
         }
 
-        list_with_items.sort_by(|a, b| a.1.cmp(&b.1));
+        list_with_items.sort_by_key(|a| a.1);
 
         let mut grouping: HashMap<String, Vec<(usize, usize, usize)>> = HashMap::new();
         for ((row, col, zone), date) in list_with_items {
@@ -1108,8 +1188,23 @@ impl Warehouse {
         grouping
     }
 
+    // Zones holding an item that expired before `today`. Undated items never expire.
+    pub fn expired_zones(&self, today: NaiveDate) -> Vec<&Zone> {
+        self.rows
+            .iter()
+            .flat_map(|row| row.columns.iter())
+            .flat_map(|column| column.zones.iter())
+            .filter(|zone| {
+                zone.item
+                    .as_ref()
+                    .and_then(|item| item.expiry_date)
+                    .is_some_and(|date| date < today)
+            })
+            .collect()
+    }
+
     pub fn find_contiguous_space(
-        &mut self,
+        &self,
         required_space: usize,
     ) -> Result<(usize, usize, usize), ErrorKind> {
         if required_space > self.available_space {
@@ -1137,33 +1232,21 @@ impl Warehouse {
             required_space += items.len();
         }
         match self.find_contiguous_space(required_space) {
-            Ok((mut r, mut c, mut z)) => {
-                let first_position = (r, c, z);
+            Ok(first_position) => {
+                let mut position = Some(first_position);
+                let mut last_position = first_position;
                 for (_, items) in grouped_items.iter_mut() {
                     for item in items {
-                        let (row, column, zone) = *item;
-                        match self.move_item((row, column, zone), (r, c, z)) {
-                            Ok(_) => {}
-                            Err(e) => return Err(e),
-                        }
-                        required_space -= 1;
-                        if z + 1 == self.rows[r].columns[c].zones.len() {
-                            z = 0;
-                            c += 1;
-                            if c == self.rows[r].columns.len() {
-                                c = 0;
-                                r += 1;
-                            }
-                        } else {
-                            z += 1;
-                        }
+                        let target = position.ok_or(NoContiguousSpace)?;
+                        self.move_item(*item, target)?;
+                        last_position = target;
+                        position = self.next_position(target);
                     }
                 }
-                let last_position = (r - 1, c - 1, z - 1);
-                println!(
+                self.log.info(format!(
                     "Moved items to zones from {:?} to {:?}",
                     first_position, last_position
-                );
+                ));
                 Ok(())
             }
             Err(e) => Err(e),
@@ -1183,6 +1266,74 @@ impl Warehouse {
         }
     }
 
+    pub fn change_product_id(&mut self, product_id: u32, new_id: u32) {
+        for row in &mut self.rows {
+            for column in &mut row.columns {
+                for zone in column.occupied_zones_mut() {
+                    if let Some(item) = zone.item.as_mut() {
+                        if item.id == product_id {
+                            item.id = new_id;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Relabels up to `qty` items of `product_id` in place, returning how many changed.
+    pub fn retag_items(&mut self, product_id: u32, new_id: u32, qty: usize) -> usize {
+        let items = self.find_all_item_occurences(product_id);
+        let mut retagged = 0;
+        for (row, col, zone) in items.into_iter().take(qty) {
+            if let Some(item) = self.get_item_mut(row, col, zone) {
+                item.id = new_id;
+                retagged += 1;
+            }
+        }
+        retagged
+    }
+
+    pub fn segregate_items_by_expiration(&mut self, product_id: u32) -> Result<(), ErrorKind> {
+        let items = self.find_all_item_occurences(product_id);
+        if items.is_empty() {
+            return Err(NoProductFound);
+        }
+        let undated_items: Vec<(usize, usize, usize)> = items
+            .iter()
+            .filter(|&&(row, col, zone)| {
+                self.get_item(row, col, zone)
+                    .is_some_and(|item| item.expiry_date.is_none())
+            })
+            .copied()
+            .collect();
+        let grouped_items = self.group_items_by_expiration(items);
+        let mut dates: Vec<&String> = grouped_items.keys().collect();
+        dates.sort();
+
+        let mut ordered_items: Vec<(usize, usize, usize)> = Vec::new();
+        for date in dates {
+            ordered_items.extend(&grouped_items[date]);
+        }
+        ordered_items.extend(undated_items);
+
+        let first_position = self.find_contiguous_space(ordered_items.len())?;
+        let mut position = Some(first_position);
+        for item in ordered_items {
+            match position {
+                Some(new_position) => {
+                    self.move_item(item, new_position)?;
+                    position = self.next_position(new_position);
+                }
+                None => return Err(NoContiguousSpace),
+            }
+        }
+        self.log.info(format!(
+            "Segregated items of product {} by expiration starting at {:?}",
+            product_id, first_position
+        ));
+        Ok(())
+    }
+
     pub fn remove_all_items(&mut self, product_id: u32) -> Result<(), ErrorKind> {
         match self.find_all_item_occurences(product_id) {
             items if !items.is_empty() => {
@@ -1204,3 +1355,253 @@ impl Default for Warehouse {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_contiguous_space_in_fragmented_warehouse() {
+        let mut warehouse = Warehouse::new();
+        warehouse.initialize_rows(1, 2, 5);
+        warehouse
+            .add_item(1, 1, 2, ProductItem::new(1, 1, 1, 2, None))
+            .unwrap();
+        warehouse
+            .add_item(1, 1, 4, ProductItem::new(1, 1, 1, 4, None))
+            .unwrap();
+
+        assert_eq!(warehouse.find_contiguous_space(1).unwrap(), (1, 1, 1));
+        assert_eq!(warehouse.find_contiguous_space(3).unwrap(), (1, 1, 5));
+        assert!(matches!(
+            warehouse.find_contiguous_space(7),
+            Err(NoContiguousSpace)
+        ));
+        assert!(matches!(
+            warehouse.find_contiguous_space(9),
+            Err(InsufficientSpace)
+        ));
+    }
+
+    #[test]
+    fn test_segregate_items_by_expiration() {
+        let mut warehouse = Warehouse::new();
+        warehouse.initialize_rows(1, 3, 5);
+        let first = NaiveDate::from_ymd_opt(2025, 1, 1);
+        let second = NaiveDate::from_ymd_opt(2025, 2, 1);
+        let third = NaiveDate::from_ymd_opt(2025, 3, 1);
+        for ((c, z), date) in [
+            ((1, 1), third),
+            ((1, 2), first),
+            ((1, 4), second),
+            ((1, 5), first),
+            ((2, 2), third),
+        ] {
+            warehouse
+                .add_item(1, c, z, ProductItem::new(1, 1, c, z, date))
+                .unwrap();
+        }
+        warehouse
+            .add_item(1, 2, 4, ProductItem::new(2, 1, 2, 4, None))
+            .unwrap();
+
+        warehouse.segregate_items_by_expiration(1).unwrap();
+
+        let positions = warehouse.find_all_item_occurences(1);
+        let dates: Vec<Option<NaiveDate>> = positions
+            .iter()
+            .map(|&(r, c, z)| warehouse.get_item(r, c, z).unwrap().expiry_date)
+            .collect();
+        assert_eq!(dates, vec![first, first, second, third, third]);
+        let mut position = positions[0];
+        for next in &positions[1..] {
+            position = warehouse.next_position(position).unwrap();
+            assert_eq!(position, *next);
+        }
+        assert_eq!(warehouse.get_item(1, 2, 4).unwrap().id, 2);
+    }
+
+    #[test]
+    fn test_recompute_counters_fixes_corruption() {
+        let mut warehouse = Warehouse::new();
+        warehouse.initialize_rows(2, 2, 3);
+        warehouse
+            .add_item(1, 2, 1, ProductItem::new(1, 1, 2, 1, None))
+            .unwrap();
+        warehouse
+            .add_item(2, 1, 3, ProductItem::new(2, 2, 1, 3, None))
+            .unwrap();
+
+        warehouse.available_space = 0;
+        warehouse.capacity = 99;
+        warehouse.rows[0].available_space = 7;
+        warehouse.rows[1].columns[0].available_space = 3;
+        warehouse.rows[1].columns[1].capacity = 0;
+
+        warehouse.recompute_counters();
+        assert_eq!(warehouse.capacity, 12);
+        assert_eq!(warehouse.available_space, 10);
+        assert_eq!(warehouse.rows[0].available_space, 5);
+        assert_eq!(warehouse.rows[1].available_space, 5);
+        assert_eq!(warehouse.rows[1].columns[0].available_space, 2);
+        assert_eq!(warehouse.rows[1].columns[1].capacity, 3);
+    }
+
+    #[test]
+    fn test_expired_zones() {
+        let mut warehouse = Warehouse::new();
+        warehouse.initialize_rows(1, 2, 3);
+        let past = NaiveDate::from_ymd_opt(2024, 5, 1);
+        let future = NaiveDate::from_ymd_opt(2026, 5, 1);
+        warehouse
+            .add_item(1, 1, 2, ProductItem::new(1, 1, 1, 2, past))
+            .unwrap();
+        warehouse
+            .add_item(1, 2, 1, ProductItem::new(2, 1, 2, 1, future))
+            .unwrap();
+        warehouse
+            .add_item(1, 2, 3, ProductItem::new(3, 1, 2, 3, None))
+            .unwrap();
+
+        let today = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let expired: Vec<((usize, usize, usize), u32)> = warehouse
+            .expired_zones(today)
+            .iter()
+            .map(|zone| (zone.pos(), zone.item.as_ref().unwrap().id))
+            .collect();
+        assert_eq!(expired, vec![((1, 1, 2), 1)]);
+    }
+
+    #[test]
+    fn test_layout_csv_round_trip() {
+        let mut warehouse = Warehouse::new();
+        warehouse.initialize_rows(2, 2, 3);
+        let items = [(1, 1, 1, 7), (1, 2, 3, 8), (2, 1, 2, 7), (2, 2, 1, 12)];
+        for (r, c, z, id) in items {
+            warehouse
+                .add_item(r, c, z, ProductItem::new(id, r, c, z, None))
+                .unwrap();
+        }
+
+        let csv = warehouse.layout_csv();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("row,column,zone 1,zone 2,zone 3"));
+        let mut occupied = Vec::new();
+        for line in lines {
+            let cells: Vec<&str> = line.split(',').collect();
+            assert_eq!(cells.len(), 5);
+            let row: usize = cells[0].parse().unwrap();
+            let column: usize = cells[1].parse().unwrap();
+            for (zone, cell) in cells[2..].iter().enumerate() {
+                if !cell.is_empty() {
+                    occupied.push((row, column, zone + 1, cell.parse::<u32>().unwrap()));
+                }
+            }
+        }
+        assert_eq!(occupied, items);
+    }
+
+    #[test]
+    fn test_next_position() {
+        let mut warehouse = Warehouse::new();
+        warehouse.initialize_rows(2, 2, 3);
+        assert_eq!(warehouse.next_position((1, 1, 1)), Some((1, 1, 2)));
+        assert_eq!(warehouse.next_position((1, 1, 3)), Some((1, 2, 1)));
+        assert_eq!(warehouse.next_position((1, 2, 3)), Some((2, 1, 1)));
+        assert_eq!(warehouse.next_position((2, 2, 2)), Some((2, 2, 3)));
+        assert_eq!(warehouse.next_position((2, 2, 3)), None);
+        assert_eq!(warehouse.next_position((3, 1, 1)), None);
+    }
+
+    fn product_zones(warehouse: &Warehouse) -> Vec<Option<u32>> {
+        warehouse
+            .rows
+            .iter()
+            .flat_map(|row| row.columns.iter())
+            .flat_map(|column| column.zones.iter())
+            .map(|zone| zone.item.as_ref().map(|item| item.id))
+            .collect()
+    }
+
+    #[test]
+    fn test_add_items_across_row_boundary() {
+        let mut warehouse = Warehouse::new();
+        warehouse.initialize_rows(2, 2, 3);
+        warehouse.add_items_by_qty(1, 8, None).unwrap();
+
+        let mut expected = vec![Some(1); 8];
+        expected.extend([None; 4]);
+        assert_eq!(product_zones(&warehouse), expected);
+        assert_eq!(warehouse.available_space, 4);
+        assert_eq!(warehouse.rows[0].available_space, 0);
+        assert_eq!(warehouse.rows[1].available_space, 4);
+        assert_eq!(warehouse.rows[1].columns[0].available_space, 1);
+    }
+
+    #[test]
+    fn test_add_to_contiguous_product_across_row_boundary() {
+        let mut warehouse = Warehouse::new();
+        warehouse.initialize_rows(2, 2, 3);
+        warehouse.add_items_by_qty(2, 5, None).unwrap();
+        warehouse.add_items_by_qty(1, 1, None).unwrap();
+        warehouse.add_items_by_qty(1, 3, None).unwrap();
+
+        let mut expected = vec![Some(2); 5];
+        expected.extend([Some(1); 4]);
+        expected.extend([None; 3]);
+        assert_eq!(product_zones(&warehouse), expected);
+        assert_eq!(
+            warehouse.find_all_item_occurences(1),
+            vec![(1, 2, 3), (2, 1, 1), (2, 1, 2), (2, 1, 3)]
+        );
+        let available_space = warehouse.available_space;
+        warehouse.recompute_counters();
+        assert_eq!(warehouse.available_space, available_space);
+        assert_eq!(available_space, 3);
+    }
+
+    #[test]
+    fn test_flat_map_diff_after_move() {
+        let mut warehouse = Warehouse::new();
+        warehouse.initialize_rows(1, 2, 3);
+        warehouse.add_items_by_qty(1, 2, None).unwrap();
+        let before = warehouse.flat_map();
+        warehouse.move_item((1, 1, 2), (1, 2, 1)).unwrap();
+        let after = warehouse.flat_map();
+
+        assert_eq!(
+            warehouse.flat_map_diff(&before, &after),
+            vec![
+                ((1, 1, 2), ZoneChange::Freed),
+                ((1, 2, 1), ZoneChange::Occupied),
+            ]
+        );
+        assert!(warehouse.flat_map_diff(&after, &after).is_empty());
+    }
+
+    #[test]
+    fn test_add_to_product_at_the_end_with_free_space_before() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 1);
+        let mut warehouse = Warehouse::new();
+        warehouse.initialize_rows(1, 2, 5);
+        warehouse.add_items_by_qty(2, 6, None).unwrap();
+        warehouse.add_items_by_qty(1, 3, date).unwrap();
+        warehouse.remove_all_items(2).unwrap();
+        assert_eq!(warehouse.flat_map(), "0000001110");
+
+        warehouse.add_items_by_qty(1, 2, date).unwrap();
+        assert_eq!(warehouse.flat_map(), "1111100000");
+        assert_eq!(warehouse.find_all_item_occurences(1).len(), 5);
+        assert_eq!(warehouse.available_space, 5);
+
+        // With no block big enough anywhere the warehouse is left unchanged.
+        let mut warehouse = Warehouse::new();
+        warehouse.initialize_rows(1, 2, 3);
+        warehouse.add_items_by_qty(2, 3, None).unwrap();
+        warehouse.add_items_by_qty(1, 2, date).unwrap();
+        warehouse.remove_all_items(2).unwrap();
+        assert!(warehouse.add_items_by_qty(1, 2, date).is_err());
+        assert_eq!(warehouse.flat_map(), "000110");
+        assert_eq!(warehouse.available_space, 4);
+    }
+}
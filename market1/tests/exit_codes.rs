@@ -0,0 +1,19 @@
+use std::process::Command;
+
+fn exit_code(args: &[&str]) -> Option<i32> {
+    Command::new(env!("CARGO_BIN_EXE_storage_control"))
+        .args(args)
+        .output()
+        .ok()
+        .and_then(|output| output.status.code())
+}
+
+#[test]
+fn unknown_command_exits_with_2() {
+    assert_eq!(exit_code(&["frobnicate", "now"]), Some(2));
+}
+
+#[test]
+fn missing_storage_file_exits_with_1() {
+    assert_eq!(exit_code(&["load", "./no-such-storage.json"]), Some(1));
+}
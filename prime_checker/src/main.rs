@@ -1,46 +1,344 @@
+use std::fmt::{self, Display, Formatter};
 use std::io::prelude::*;
 use std::io::{stdin, stdout};
 
-fn main() {
-    fn check_prime(n: u32) -> bool {
-        if n <= 1 {
-            return false;
-        } else if n <= 3 {
-            return true;
-        } else if n % 2 == 0 || n % 3 == 0 {
+fn check_prime(n: u32) -> bool {
+    if n <= 1 {
+        return false;
+    } else if n <= 3 {
+        return true;
+    } else if n.is_multiple_of(2) || n.is_multiple_of(3) {
+        return false;
+    }
+
+    // The bound is checked as i * i <= n in u64, so neither rounding in a
+    // float square root nor i * i overflowing a u32 can end the loop early.
+    let n = n as u64;
+    let mut i: u64 = 5;
+    while i * i <= n {
+        if n.is_multiple_of(i) || n.is_multiple_of(i + 2) {
             return false;
         }
+        i += 6;
+    }
+
+    true
+}
+
+// The smallest prime above n, or None when there is none that fits in a u32.
+fn next_prime(n: u32) -> Option<u32> {
+    let mut candidate = n.checked_add(1)?;
+    while !check_prime(candidate) {
+        candidate = candidate.checked_add(1)?;
+    }
+    Some(candidate)
+}
+
+// Number of primes below 2^32.
+const U32_PRIME_COUNT: u32 = 203_280_221;
+
+// Counts from 1, so nth_prime(1) is 2. None for 0 or past the largest u32
+// prime, which is rejected before searching.
+fn nth_prime(k: u32) -> Option<u32> {
+    if k == 0 || k > U32_PRIME_COUNT {
+        return None;
+    }
+    let mut prime = 2;
+    for _ in 1..k {
+        prime = next_prime(prime)?;
+    }
+    Some(prime)
+}
+
+// The sieve holds a bool for every number up to its limit, so the REPL keeps
+// limits to what fits comfortably in memory.
+const SIEVE_LIMIT: u32 = 10_000_000;
+
+fn parse_limit(limit: &str) -> Option<u32> {
+    limit
+        .parse::<u32>()
+        .ok()
+        .filter(|&limit| limit <= SIEVE_LIMIT)
+}
 
-        let mut i = 5;
-        while i <= (n as f64).sqrt() as u32 {
-            if n % i == 0 || n % (i + 2) == 0 {
-                return false;
+// Every prime up to and including n.
+fn sieve(n: u32) -> Vec<u32> {
+    let n = n as usize;
+    let mut is_prime = vec![true; n + 1];
+    is_prime[0] = false;
+    if n >= 1 {
+        is_prime[1] = false;
+    }
+    let mut i = 2;
+    while i * i <= n {
+        if is_prime[i] {
+            for multiple in (i * i..=n).step_by(i) {
+                is_prime[multiple] = false;
             }
-            i += 6;
         }
+        i += 1;
+    }
+    is_prime
+        .iter()
+        .enumerate()
+        .filter(|(_, &prime)| prime)
+        .map(|(i, _)| i as u32)
+        .collect()
+}
+
+fn reverse_digits(mut n: u64) -> u64 {
+    let mut reversed = 0;
+    while n > 0 {
+        reversed = reversed * 10 + n % 10;
+        n /= 10;
+    }
+    reversed
+}
+
+// Compares digits from both ends, so it can't overflow the way comparing
+// against reverse_digits(n) could for large n.
+fn is_palindrome(n: u64) -> bool {
+    let digits = n.to_string();
+    digits.chars().eq(digits.chars().rev())
+}
+
+// Primes up to and including limit that read the same in both directions.
+fn palindromic_primes(limit: u32) -> Vec<u32> {
+    sieve(limit)
+        .into_iter()
+        .filter(|&n| is_palindrome(n as u64))
+        .collect()
+}
+
+#[derive(Debug, PartialEq)]
+enum CollatzError {
+    Zero,
+    // Holds the last value reached before 3n + 1 overflowed.
+    Overflow(u64),
+}
+
+impl Display for CollatzError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            CollatzError::Zero => write!(f, "The Collatz sequence starts at 1 or above"),
+            CollatzError::Overflow(n) => write!(f, "Overflow computing 3 * {} + 1", n),
+        }
+    }
+}
+
+fn collatz_next(n: u64) -> Result<u64, CollatzError> {
+    if n.is_multiple_of(2) {
+        Ok(n / 2)
+    } else {
+        n.checked_mul(3)
+            .and_then(|m| m.checked_add(1))
+            .ok_or(CollatzError::Overflow(n))
+    }
+}
 
-        true
+// Every value from n down to 1, both included.
+fn collatz_sequence(n: u64) -> Result<Vec<u64>, CollatzError> {
+    if n == 0 {
+        return Err(CollatzError::Zero);
+    }
+    let mut sequence = vec![n];
+    let mut current = n;
+    while current != 1 {
+        current = collatz_next(current)?;
+        sequence.push(current);
     }
+    Ok(sequence)
+}
 
+fn collatz_steps(n: u64) -> Result<u64, CollatzError> {
+    if n == 0 {
+        return Err(CollatzError::Zero);
+    }
+    let mut steps = 0;
+    let mut current = n;
+    while current != 1 {
+        current = collatz_next(current)?;
+        steps += 1;
+    }
+    Ok(steps)
+}
+
+fn main() {
     loop {
         let mut input = String::new();
-        print!("Enter number: ");
+        print!("Enter number, next <number>, nth <k>, primes <limit>, reverse <number>, palindromic-primes <limit>, collatz <number> or exit: ");
         stdout().flush().unwrap();
         stdin().read_line(&mut input).expect("Failed to read line");
 
-        let n: u32 = match input.trim().parse() {
-            Ok(num) => num,
-            Err(_) => {
-                println!("Invalid input");
-                return;
+        match input.split_whitespace().collect::<Vec<&str>>()[..] {
+            ["primes", limit] => {
+                match parse_limit(limit) {
+                    Some(limit) => {
+                        let primes: Vec<String> =
+                            sieve(limit).iter().map(|p| p.to_string()).collect();
+                        println!("{}", primes.join(", "));
+                    }
+                    None => println!("Invalid limit, use a number up to {}", SIEVE_LIMIT),
+                }
+                continue;
             }
-        };
-        let result: bool = check_prime(n);
-
-        if result {
-            println!("{} is a prime number", n);
-        } else {
-            println!("{} is not a prime number", n);
+            ["palindromic-primes", limit] => {
+                match parse_limit(limit) {
+                    Some(limit) => {
+                        let primes: Vec<String> = palindromic_primes(limit)
+                            .iter()
+                            .map(|p| p.to_string())
+                            .collect();
+                        println!("{}", primes.join(", "));
+                    }
+                    None => println!("Invalid limit, use a number up to {}", SIEVE_LIMIT),
+                }
+                continue;
+            }
+            // Reversing a u32 always fits in a u64.
+            ["reverse", n] => {
+                match n.parse::<u32>() {
+                    Ok(n) => println!("{}", reverse_digits(n as u64)),
+                    Err(_) => println!("Invalid input"),
+                }
+                continue;
+            }
+            ["collatz", n] => {
+                match n.parse::<u64>() {
+                    Ok(n) => match (collatz_sequence(n), collatz_steps(n)) {
+                        (Ok(sequence), Ok(steps)) => {
+                            let values: Vec<String> =
+                                sequence.iter().map(|v| v.to_string()).collect();
+                            println!("{}", values.join(" -> "));
+                            println!("{} steps", steps);
+                        }
+                        (Err(e), _) | (_, Err(e)) => println!("{}", e),
+                    },
+                    Err(_) => println!("Invalid input"),
+                }
+                continue;
+            }
+            ["next", n] => match n.parse::<u32>() {
+                Ok(n) => match next_prime(n) {
+                    Some(prime) => println!("{}", prime),
+                    None => println!("There is no prime above {} that fits in a u32", n),
+                },
+                Err(_) => println!("Invalid input"),
+            },
+            ["nth", k] => match k.parse::<u32>() {
+                Ok(k) => match nth_prime(k) {
+                    Some(prime) => println!("{}", prime),
+                    None if k == 0 => println!("Primes are counted from 1"),
+                    None => println!("The prime number {} does not fit in a u32", k),
+                },
+                Err(_) => println!("Invalid input"),
+            },
+            [] | ["exit"] => return,
+            ["prime", n] | [n] => match n.parse::<u32>() {
+                Ok(n) if check_prime(n) => println!("{} is a prime number", n),
+                Ok(n) => println!("{} is not a prime number", n),
+                Err(_) => println!("Invalid input"),
+            },
+            _ => println!("Invalid input"),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sieve_matches_check_prime() {
+        let expected: Vec<u32> = (0..=10000).filter(|&n| check_prime(n)).collect();
+        assert_eq!(sieve(10000), expected);
+        assert_eq!(sieve(0), Vec::<u32>::new());
+        assert_eq!(sieve(1), Vec::<u32>::new());
+        assert_eq!(sieve(2), vec![2]);
+    }
+
+    #[test]
+    fn test_check_prime_near_u32_max() {
+        assert!(check_prime(4294967291));
+        assert!(check_prime(4294967279));
+        assert!(!check_prime(4294967289));
+        assert!(!check_prime(u32::MAX));
+        // Squares and near squares of the largest 16-bit prime, where the
+        // bound has to reach the square root exactly.
+        assert!(!check_prime(65521 * 65521));
+        assert!(!check_prime(65519 * 65521));
+        assert!(check_prime(65521));
+    }
+
+    #[test]
+    fn test_next_prime() {
+        assert_eq!(next_prime(0), Some(2));
+        assert_eq!(next_prime(2), Some(3));
+        assert_eq!(next_prime(100), Some(101));
+        assert_eq!(next_prime(4294967290), Some(4294967291));
+        assert_eq!(next_prime(4294967291), None);
+        assert_eq!(next_prime(u32::MAX), None);
+    }
+
+    #[test]
+    fn test_nth_prime() {
+        let first: Vec<u32> = (1..=10).filter_map(nth_prime).collect();
+        assert_eq!(first, vec![2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+        assert_eq!(nth_prime(100), Some(541));
+        assert_eq!(nth_prime(0), None);
+        assert_eq!(nth_prime(U32_PRIME_COUNT + 1), None);
+        assert_eq!(nth_prime(u32::MAX), None);
+    }
+
+    #[test]
+    fn test_parse_limit() {
+        assert_eq!(parse_limit("100"), Some(100));
+        assert_eq!(parse_limit("10000000"), Some(SIEVE_LIMIT));
+        assert_eq!(parse_limit("10000001"), None);
+        assert_eq!(parse_limit("4294967295"), None);
+        assert_eq!(parse_limit("-1"), None);
+    }
+
+    #[test]
+    fn test_reverse_digits() {
+        assert_eq!(reverse_digits(123), 321);
+        assert_eq!(reverse_digits(120), 21);
+        assert_eq!(reverse_digits(0), 0);
+    }
+
+    #[test]
+    fn test_is_palindrome() {
+        assert!(is_palindrome(12321));
+        assert!(is_palindrome(7));
+        assert!(!is_palindrome(123));
+        assert!(!is_palindrome(10));
+    }
+
+    #[test]
+    fn test_palindromic_primes() {
+        assert_eq!(palindromic_primes(100), vec![2, 3, 5, 7, 11]);
+        assert_eq!(
+            palindromic_primes(200),
+            vec![2, 3, 5, 7, 11, 101, 131, 151, 181, 191]
+        );
+        assert!(palindromic_primes(1).is_empty());
+    }
+
+    #[test]
+    fn test_collatz_steps() {
+        assert_eq!(collatz_steps(1), Ok(0));
+        assert_eq!(collatz_steps(6), Ok(8));
+        assert_eq!(collatz_steps(27), Ok(111));
+        assert_eq!(collatz_steps(0), Err(CollatzError::Zero));
+        assert_eq!(
+            collatz_steps(u64::MAX),
+            Err(CollatzError::Overflow(u64::MAX))
+        );
+    }
+
+    #[test]
+    fn test_collatz_sequence() {
+        assert_eq!(collatz_sequence(6), Ok(vec![6, 3, 10, 5, 16, 8, 4, 2, 1]));
+        assert_eq!(collatz_sequence(0), Err(CollatzError::Zero));
+    }
+}
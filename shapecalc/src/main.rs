@@ -1,7 +1,12 @@
 pub mod shapes;
 
-use clap::{Args, Parser, Subcommand};
-use shapes::{Shape, ThreeDShape, TwoDShape};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use shapes::{ErrorKind, Shape, ThreeDShape, TwoDShape};
+use std::{
+    cmp::Ordering,
+    fs,
+    io::{self, BufRead, Write},
+};
 
 #[derive(Parser, Debug)]
 #[clap(name = "shape_calculator", about = "Calculate the area, volume or perimeter of a shape")]
@@ -10,14 +15,55 @@ struct Cli {
     cmd: Command,
 }
 
+#[derive(Parser, Debug)]
+#[clap(no_binary_name = true)]
+struct BatchLine {
+    #[clap(subcommand)]
+    calculation: Calculation,
+}
+
 #[derive(Subcommand, Debug)]
 enum Command {
+    #[command(flatten)]
+    Calculation(Calculation),
+    #[clap(name = "batch", about = "Run one calculation per line of a file")]
+    Batch { file: String },
+}
+
+#[derive(Subcommand, Debug)]
+enum Calculation {
     #[clap(name = "area")]
     Area(AreaArgs),
     #[clap(name = "volume")]
     Volume(VolumeArgs),
     #[clap(name = "perimeter")]
     Perimeter(PerimeterArgs),
+    #[clap(name = "surface-area", about = "Total surface area of a 3D shape")]
+    SurfaceArea(VolumeArgs),
+    #[clap(name = "ratio", about = "Surface area to volume ratio of a 3D shape")]
+    Ratio(VolumeArgs),
+    #[clap(name = "total", about = "Sum the area, volume or perimeter of several shapes")]
+    Total(ShapeListArgs),
+    #[clap(name = "compare", about = "Compare the area, volume or perimeter of two shapes")]
+    Compare(ShapeListArgs),
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Metric {
+    Area,
+    Volume,
+    Perimeter,
+}
+
+#[derive(Debug, Args)]
+struct ShapeListArgs {
+    metric: Metric,
+    #[arg(
+        required = true,
+        allow_hyphen_values = true,
+        help = "Shapes followed by their dimensions, e.g. square 2 circle 1"
+    )]
+    shapes: Vec<String>,
 }
 
 #[derive(Debug, Args)]
@@ -40,96 +86,418 @@ struct PerimeterArgs {
 
 #[derive(Debug, Clone, Subcommand)]
 enum ShapeAreaArgs {
-    Square { side: f64 },
-    Circle { radius: f64 },
-    Triangle { base: f64, height: f64 },
-    Rectangle { height: f64, width: f64 },
-    Sphere { radius: f64 },
-    Cilinder { radius: f64, height: f64 },
-    Cone { radius: f64, height: f64 },
-    Cube { side: f64 },
-    Tetrahedron { side: f64 },
+    Square { side: Option<f64> },
+    Circle { radius: Option<f64> },
+    Triangle { base: Option<f64>, height: Option<f64> },
+    Rectangle { height: Option<f64>, width: Option<f64> },
+    Sphere { radius: Option<f64> },
+    Cilinder { radius: Option<f64>, height: Option<f64> },
+    Cone { radius: Option<f64>, height: Option<f64> },
+    Cube { side: Option<f64> },
+    Tetrahedron { side: Option<f64> },
+    Pyramid { base: Option<f64>, height: Option<f64> },
 }
 
 #[derive(Debug, Clone, Subcommand)]
 enum ShapeVolumeArgs {
-    Sphere { radius: f64 },
-    Cilinder { radius: f64, height: f64 },
-    Cone { radius: f64, height: f64 },
-    Cube { side: f64 },
-    Tetrahedron { side: f64 },
+    Sphere { radius: Option<f64> },
+    Cilinder { radius: Option<f64>, height: Option<f64> },
+    Cone { radius: Option<f64>, height: Option<f64> },
+    Cube { side: Option<f64> },
+    Tetrahedron { side: Option<f64> },
+    Pyramid { base: Option<f64>, height: Option<f64> },
 }
 
 #[derive(Debug, Clone, Subcommand)]
 enum ShapePerimeterArgs {
-    Square { side: f64 },
-    Circle { radius: f64 },
-    Triangle { side1: f64, side2: f64, side3: f64 },
-    Rectangle { height: f64, width: f64 },
+    Square { side: Option<f64> },
+    Circle { radius: Option<f64> },
+    Triangle { side1: Option<f64>, side2: Option<f64>, side3: Option<f64> },
+    Rectangle { height: Option<f64>, width: Option<f64> },
 }
 
-fn main() {
-    use Command::*;
-    let args: Cli = Cli::parse();
+// Supplies a dimension that was left out on the command line.
+type Fill<'a> = &'a mut dyn FnMut(&'static str) -> Result<f64, ErrorKind>;
 
-    match args.cmd {
-        Area(args) => {
-            use ShapeAreaArgs::*;
-            let shape = match args.shape {
-                Square { side } => Shape::TwoD(TwoDShape::Square { side }),
-                Circle { radius } => Shape::TwoD(TwoDShape::Circle { radius }),
-                Triangle { base, height } => Shape::TwoD(TwoDShape::Triangle { base, height, side2: 0.0, side3: 0.0 }),
-                Rectangle { height, width } => Shape::TwoD(TwoDShape::Rectangle { height, width }),
-                Sphere { radius } => Shape::ThreeD(ThreeDShape::Sphere { radius }),
-                Cilinder { radius, height } => Shape::ThreeD(ThreeDShape::Cilinder { radius, height }),
-                Cone { radius, height } => Shape::ThreeD(ThreeDShape::Cone { radius, height }),
-                Cube { side } => Shape::ThreeD(ThreeDShape::Cube { side }),
-                Tetrahedron { side } => Shape::ThreeD(ThreeDShape::Tetrahedron { side }),
-            };
-            let area = match shape.area() {
-                Ok(area) => area,
-                Err(e) => {
-                    eprintln!("Error: {}", e);
-                    return;
-                }
-            };
-            println!("Area: {}", area);
+fn dim(value: Option<f64>, name: &'static str, fill: Fill) -> Result<f64, ErrorKind> {
+    match value {
+        Some(value) => Ok(value),
+        None => fill(name),
+    }
+}
+
+impl ShapeAreaArgs {
+    fn into_shape(self, fill: Fill) -> Result<Shape, ErrorKind> {
+        use ShapeAreaArgs::*;
+        Ok(match self {
+            Square { side } => Shape::TwoD(TwoDShape::Square { side: dim(side, "side", fill)? }),
+            Circle { radius } => Shape::TwoD(TwoDShape::Circle { radius: dim(radius, "radius", fill)? }),
+            Triangle { base, height } => Shape::TwoD(TwoDShape::Triangle {
+                base: dim(base, "base", fill)?,
+                height: dim(height, "height", fill)?,
+                side2: 0.0,
+                side3: 0.0,
+            }),
+            Rectangle { height, width } => Shape::TwoD(TwoDShape::Rectangle {
+                height: dim(height, "height", fill)?,
+                width: dim(width, "width", fill)?,
+            }),
+            Sphere { radius } => Shape::ThreeD(ThreeDShape::Sphere { radius: dim(radius, "radius", fill)? }),
+            Cilinder { radius, height } => Shape::ThreeD(ThreeDShape::Cilinder {
+                radius: dim(radius, "radius", fill)?,
+                height: dim(height, "height", fill)?,
+            }),
+            Cone { radius, height } => Shape::ThreeD(ThreeDShape::Cone {
+                radius: dim(radius, "radius", fill)?,
+                height: dim(height, "height", fill)?,
+            }),
+            Cube { side } => Shape::ThreeD(ThreeDShape::Cube { side: dim(side, "side", fill)? }),
+            Tetrahedron { side } => Shape::ThreeD(ThreeDShape::Tetrahedron { side: dim(side, "side", fill)? }),
+            Pyramid { base, height } => Shape::ThreeD(ThreeDShape::Pyramid {
+                base: dim(base, "base", fill)?,
+                height: dim(height, "height", fill)?,
+            }),
+        })
+    }
+}
+
+impl ShapeVolumeArgs {
+    fn into_shape(self, fill: Fill) -> Result<Shape, ErrorKind> {
+        use ShapeVolumeArgs::*;
+        Ok(match self {
+            Sphere { radius } => Shape::ThreeD(ThreeDShape::Sphere { radius: dim(radius, "radius", fill)? }),
+            Cilinder { radius, height } => Shape::ThreeD(ThreeDShape::Cilinder {
+                radius: dim(radius, "radius", fill)?,
+                height: dim(height, "height", fill)?,
+            }),
+            Cone { radius, height } => Shape::ThreeD(ThreeDShape::Cone {
+                radius: dim(radius, "radius", fill)?,
+                height: dim(height, "height", fill)?,
+            }),
+            Cube { side } => Shape::ThreeD(ThreeDShape::Cube { side: dim(side, "side", fill)? }),
+            Tetrahedron { side } => Shape::ThreeD(ThreeDShape::Tetrahedron { side: dim(side, "side", fill)? }),
+            Pyramid { base, height } => Shape::ThreeD(ThreeDShape::Pyramid {
+                base: dim(base, "base", fill)?,
+                height: dim(height, "height", fill)?,
+            }),
+        })
+    }
+}
+
+impl ShapePerimeterArgs {
+    fn into_shape(self, fill: Fill) -> Result<Shape, ErrorKind> {
+        use ShapePerimeterArgs::*;
+        Ok(match self {
+            Square { side } => Shape::TwoD(TwoDShape::Square { side: dim(side, "side", fill)? }),
+            Circle { radius } => Shape::TwoD(TwoDShape::Circle { radius: dim(radius, "radius", fill)? }),
+            Triangle { side1, side2, side3 } => Shape::TwoD(TwoDShape::Triangle {
+                base: dim(side1, "side1", fill)?,
+                height: 0.0,
+                side2: dim(side2, "side2", fill)?,
+                side3: dim(side3, "side3", fill)?,
+            }),
+            Rectangle { height, width } => Shape::TwoD(TwoDShape::Rectangle {
+                height: dim(height, "height", fill)?,
+                width: dim(width, "width", fill)?,
+            }),
+        })
+    }
+}
+
+// Used wherever there is no one to prompt, such as batch files and shape lists.
+fn no_prompt(name: &'static str) -> Result<f64, ErrorKind> {
+    Err(ErrorKind::MissingDimension(name))
+}
+
+// Asks for a dimension until it parses as a number. Running out of input
+// leaves the dimension missing.
+fn prompt_dimension(name: &'static str, input: &mut impl BufRead) -> Result<f64, ErrorKind> {
+    loop {
+        print!("Enter {}: ", name);
+        io::stdout().flush().ok();
+        let mut line = String::new();
+        match input.read_line(&mut line) {
+            Ok(0) | Err(_) => return Err(ErrorKind::MissingDimension(name)),
+            Ok(_) => {}
+        }
+        match line.trim().parse::<f64>() {
+            Ok(value) => return Ok(value),
+            Err(_) => println!("'{}' is not a number, try again", line.trim()),
+        }
+    }
+}
+
+impl Metric {
+    fn as_str(&self) -> &str {
+        match self {
+            Metric::Area => "area",
+            Metric::Volume => "volume",
+            Metric::Perimeter => "perimeter",
+        }
+    }
+
+    fn measure(&self, shape: &Shape) -> Result<f64, ErrorKind> {
+        match self {
+            Metric::Area => shape.area(),
+            Metric::Volume => shape.volume(),
+            Metric::Perimeter => shape.perimeter(),
         }
+    }
 
+    // Parses a spec such as ["rectangle", "2", "3"] with the same subcommands
+    // the metric uses on the command line.
+    fn parse_shape(&self, spec: &[String]) -> Result<Shape, ErrorKind> {
+        let args = std::iter::once(self.as_str()).chain(spec.iter().map(String::as_str));
+        match BatchLine::try_parse_from(args) {
+            Ok(batch_line) => match batch_line.calculation {
+                Calculation::Area(args) => args.shape.into_shape(&mut no_prompt),
+                Calculation::Volume(args) => args.shape.into_shape(&mut no_prompt),
+                Calculation::Perimeter(args) => args.shape.into_shape(&mut no_prompt),
+                _ => Err(ErrorKind::InvalidShape(spec.join(" "))),
+            },
+            Err(e) => Err(ErrorKind::InvalidShape(format!(
+                "{} ({})",
+                spec.join(" "),
+                clap_error_message(&e)
+            ))),
+        }
+    }
+}
+
+// Splits "square 2 circle 1" into one spec per shape, starting a new spec at
+// every token that isn't a number.
+fn split_shape_specs(tokens: &[String]) -> Vec<&[String]> {
+    let mut specs = Vec::new();
+    let mut start = 0;
+    for (i, token) in tokens.iter().enumerate().skip(1) {
+        if token.parse::<f64>().is_err() {
+            specs.push(&tokens[start..i]);
+            start = i;
+        }
+    }
+    if !tokens.is_empty() {
+        specs.push(&tokens[start..]);
+    }
+    specs
+}
+
+fn total(metric: Metric, tokens: &[String]) -> Result<f64, ErrorKind> {
+    let mut sum = 0.0;
+    for spec in split_shape_specs(tokens) {
+        let shape = metric.parse_shape(spec)?;
+        sum += metric.measure(&shape)?;
+    }
+    Ok(sum)
+}
+
+// Returns how the first shape compares to the second, the absolute difference
+// and that difference as a percentage of the smaller measurement.
+fn compare(metric: Metric, tokens: &[String]) -> Result<(Ordering, f64, f64), ErrorKind> {
+    let specs = split_shape_specs(tokens);
+    if specs.len() != 2 {
+        return Err(ErrorKind::ExpectedTwoShapes(specs.len()));
+    }
+    let first = metric.measure(&metric.parse_shape(specs[0])?)?;
+    let second = metric.measure(&metric.parse_shape(specs[1])?)?;
+    let difference = (first - second).abs();
+    if difference <= f64::EPSILON * first.abs().max(second.abs()) {
+        return Ok((Ordering::Equal, 0.0, 0.0));
+    }
+    let percentage = difference / first.min(second) * 100.0;
+    let ordering = if first > second {
+        Ordering::Greater
+    } else {
+        Ordering::Less
+    };
+    Ok((ordering, difference, percentage))
+}
+
+// Keeps only the first paragraph of clap's message, without the usage text.
+fn clap_error_message(e: &clap::Error) -> String {
+    let message = e.to_string();
+    let summary: Vec<&str> = message
+        .lines()
+        .take_while(|line| !line.trim().is_empty())
+        .map(str::trim)
+        .collect();
+    summary.join(" ").trim_start_matches("error: ").to_string()
+}
+
+fn calculate(calculation: Calculation, fill: Fill) -> Result<String, ErrorKind> {
+    use Calculation::*;
+    match calculation {
+        Area(args) => {
+            let area = args.shape.into_shape(fill)?.area()?;
+            Ok(format!("Area: {}", area))
+        }
         Volume(args) => {
-            use ShapeVolumeArgs::*;
-            let shape = match args.shape {
-                Sphere { radius } => Shape::ThreeD(ThreeDShape::Sphere { radius }),
-                Cilinder { radius, height } => Shape::ThreeD(ThreeDShape::Cilinder { radius, height }),
-                Cone { radius, height } => Shape::ThreeD(ThreeDShape::Cone { radius, height }),
-                Cube { side } => Shape::ThreeD(ThreeDShape::Cube { side }),
-                Tetrahedron { side } => Shape::ThreeD(ThreeDShape::Tetrahedron { side }),
-            };
-            let volume = match shape.volume() {
-                Ok(volume) => volume,
-                Err(e) => {
-                    eprintln!("Error: {}", e);
-                    return;
-                }
-            };
-            println!("Volume: {}", volume);
+            let volume = args.shape.into_shape(fill)?.volume()?;
+            Ok(format!("Volume: {}", volume))
         }
         Perimeter(args) => {
-            use ShapePerimeterArgs::*;
-            let shape = match args.shape {
-                Square { side } => Shape::TwoD(TwoDShape::Square { side }),
-                Circle { radius } => Shape::TwoD(TwoDShape::Circle { radius }),
-                Triangle { side1, side2, side3 } => Shape::TwoD(TwoDShape::Triangle { base: side1, height: 0.0, side2, side3 }),
-                Rectangle { height, width } => Shape::TwoD(TwoDShape::Rectangle { height, width }),
+            let perimeter = args.shape.into_shape(fill)?.perimeter()?;
+            Ok(format!("Perimeter: {}", perimeter))
+        }
+        SurfaceArea(args) => {
+            let surface_area = args.shape.into_shape(fill)?.surface_area()?;
+            Ok(format!("Surface area: {}", surface_area))
+        }
+        Ratio(args) => {
+            let shape = args.shape.into_shape(fill)?;
+            let ratio = shape.surface_to_volume_ratio()?;
+            Ok(format!(
+                "Area: {}\nVolume: {}\nRatio: {}",
+                shape.area()?,
+                shape.volume()?,
+                ratio
+            ))
+        }
+        Total(args) => {
+            let sum = total(args.metric, &args.shapes)?;
+            Ok(format!("Total {}: {}", args.metric.as_str(), sum))
+        }
+        Compare(args) => {
+            let (ordering, difference, percentage) = compare(args.metric, &args.shapes)?;
+            let specs = split_shape_specs(&args.shapes);
+            let (larger, smaller) = match ordering {
+                Ordering::Equal => return Ok(format!("The {}s are equal", args.metric.as_str())),
+                Ordering::Greater => (specs[0].join(" "), specs[1].join(" ")),
+                Ordering::Less => (specs[1].join(" "), specs[0].join(" ")),
             };
-            let perimeter = match shape.perimeter() {
-                Ok(perimeter) => perimeter,
-                Err(e) => {
-                    eprintln!("Error: {}", e);
-                    return;
+            Ok(format!(
+                "{} is larger than {} by {} ({:.2}%)",
+                larger, smaller, difference, percentage
+            ))
+        }
+    }
+}
+
+// Calculates every non-empty line of the input on its own, so a bad line
+// only produces an error line instead of stopping the whole batch.
+fn run_batch(input: &str) -> Vec<String> {
+    let mut results = Vec::new();
+    for (number, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let result = match BatchLine::try_parse_from(line.split_whitespace()) {
+            Ok(batch_line) => calculate(batch_line.calculation, &mut no_prompt).map_err(|e| e.to_string()),
+            Err(e) => Err(clap_error_message(&e)),
+        };
+        match result {
+            Ok(output) => results.push(output),
+            Err(e) => results.push(format!("Error on line {}: {}", number + 1, e)),
+        }
+    }
+    results
+}
+
+fn main() {
+    let args: Cli = Cli::parse();
+
+    match args.cmd {
+        Command::Calculation(calculation) => {
+            let mut fill = |name| prompt_dimension(name, &mut io::stdin().lock());
+            match calculate(calculation, &mut fill) {
+                Ok(output) => println!("{}", output),
+                Err(e) => eprintln!("Error: {}", e),
+            }
+        }
+        Command::Batch { file } => match fs::read_to_string(&file) {
+            Ok(input) => {
+                for line in run_batch(&input) {
+                    println!("{}", line);
                 }
-            };
-            println!("Perimeter: {}", perimeter);
+            }
+            Err(e) => eprintln!("Error: {}", e),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_continues_past_errors() {
+        let input = "area circle 2\nvolume square 3\nvolume cube 3\n";
+        let results = run_batch(input);
+        assert_eq!(results.len(), 3);
+        assert!(results[0].starts_with("Area: 12.566"));
+        assert!(results[1].starts_with("Error on line 2"));
+        assert_eq!(results[2], "Volume: 27");
+    }
+
+    #[test]
+    fn test_surface_area() {
+        let pi = std::f64::consts::PI;
+        let cilinder = Shape::ThreeD(ThreeDShape::Cilinder {
+            radius: 1.0,
+            height: 2.0,
+        });
+        assert!((cilinder.surface_area().unwrap() - 6.0 * pi).abs() < 1e-12);
+        let square = Shape::TwoD(TwoDShape::Square { side: 2.0 });
+        assert!(matches!(square.surface_area(), Err(ErrorKind::NotA3DShape)));
+
+        let results = run_batch("surface-area cube 2\nsurface-area square 2\n");
+        assert_eq!(results[0], "Surface area: 24");
+        assert!(results[1].starts_with("Error on line 2"));
+    }
+
+    fn tokens(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_total_sums_each_shape() {
+        let sum = total(Metric::Area, &tokens("square 2 circle 1 rectangle 2 3")).unwrap();
+        assert!((sum - (4.0 + std::f64::consts::PI + 6.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_total_names_invalid_shape() {
+        match total(Metric::Volume, &tokens("cube 2 square 3")) {
+            Err(ErrorKind::InvalidShape(spec)) => assert!(spec.starts_with("square 3")),
+            other => panic!("expected an invalid shape error, got {:?}", other),
         }
     }
+
+    #[test]
+    fn test_compare_identifies_larger_shape() {
+        let (ordering, difference, percentage) =
+            compare(Metric::Area, &tokens("circle 1 square 2")).unwrap();
+        let pi = std::f64::consts::PI;
+        assert_eq!(ordering, Ordering::Less);
+        assert!((difference - (4.0 - pi)).abs() < 1e-12);
+        assert!((percentage - (4.0 - pi) / pi * 100.0).abs() < 1e-9);
+
+        let (ordering, ..) = compare(Metric::Perimeter, &tokens("square 1 rectangle 1 1")).unwrap();
+        assert_eq!(ordering, Ordering::Equal);
+    }
+
+    #[test]
+    fn test_prompt_dimension_reasks_until_number() {
+        let mut input = "abc\n\n3.5\n".as_bytes();
+        assert_eq!(prompt_dimension("base", &mut input).unwrap(), 3.5);
+        let mut empty = "".as_bytes();
+        assert!(matches!(
+            prompt_dimension("height", &mut empty),
+            Err(ErrorKind::MissingDimension("height"))
+        ));
+    }
+
+    #[test]
+    fn test_missing_dimensions_are_filled() {
+        let batch_line = BatchLine::try_parse_from(["area", "triangle", "4"]).unwrap();
+        let mut asked = Vec::new();
+        let mut fill = |name| {
+            asked.push(name);
+            Ok(3.0)
+        };
+        assert_eq!(calculate(batch_line.calculation, &mut fill).unwrap(), "Area: 6");
+        assert_eq!(asked, ["height"]);
+
+        let results = run_batch("area triangle\n");
+        assert_eq!(results[0], "Error on line 1: Missing base");
+    }
 }
@@ -40,6 +40,10 @@ pub enum ThreeDShape {
     Tetrahedron {
         side: f64,
     },
+    Pyramid {
+        base: f64,
+        height: f64,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -49,27 +53,64 @@ pub enum Shape {
 }
 
 impl TwoDShape {
-    pub fn area(&self) -> f64 {
+    pub fn area(&self) -> Result<f64, ErrorKind> {
+        use TwoDShape::*;
+        match self {
+            Square { side } => Ok(side * side),
+            Circle { radius } => Ok(PI * radius * radius),
+            Triangle { base, height, side2: _, side3: _ } => {
+                if *base <= 0.0 || *height <= 0.0 {
+                    return Err(ErrorKind::InvalidTriangle);
+                }
+                Ok(0.5 * base * height)
+            }
+            Rectangle { width, height } => Ok(width * height),
+        }
+    }
+
+    pub fn perimeter(&self) -> Result<f64, ErrorKind> {
+        use TwoDShape::*;
+        match self {
+            Square { side } => Ok(4.0 * side),
+            Circle { radius } => Ok(2.0 * PI * radius),
+            Rectangle { width, height } => Ok(2.0 * (width + height)),
+            Triangle { base, side2, side3, height: _ } => {
+                if !is_valid_triangle(*base, *side2, *side3) {
+                    return Err(ErrorKind::InvalidTriangle);
+                }
+                Ok(base + side2 + side3)
+            }
+        }
+    }
+
+    // A triangle is built from its base and height for the area, and from
+    // its three sides for the perimeter, so each only checks what it uses.
+    fn area_dimensions(&self) -> Vec<(&'static str, f64)> {
         use TwoDShape::*;
         match self {
-            Square { side } => side * side,
-            Circle { radius } => PI * radius * radius,
-            Triangle { base, height, side2: _, side3: _ } => 0.5 * base * height,
-            Rectangle { width, height } => width * height,
+            Square { side } => vec![("side", *side)],
+            Circle { radius } => vec![("radius", *radius)],
+            Triangle { base, height, .. } => vec![("base", *base), ("height", *height)],
+            Rectangle { width, height } => vec![("width", *width), ("height", *height)],
         }
     }
 
-    pub fn perimeter(&self) -> f64 {
+    fn perimeter_dimensions(&self) -> Vec<(&'static str, f64)> {
         use TwoDShape::*;
         match self {
-            Square { side } => 4.0 * side,
-            Circle { radius } => 2.0 * PI * radius,
-            Rectangle { width, height } => 2.0 * (width + height),
-            Triangle { base, side2, side3, height: _ } => base + side2 + side3,
+            Triangle { base, side2, side3, .. } => {
+                vec![("side1", *base), ("side2", *side2), ("side3", *side3)]
+            }
+            _ => self.area_dimensions(),
         }
     }
 }
 
+// Every side must be positive and shorter than the other two combined.
+fn is_valid_triangle(a: f64, b: f64, c: f64) -> bool {
+    a > 0.0 && b > 0.0 && c > 0.0 && a + b > c && a + c > b && b + c > a
+}
+
 impl ThreeDShape {
     pub fn volume(&self) -> f64 {
         use ThreeDShape::*;
@@ -79,6 +120,19 @@ impl ThreeDShape {
             Cone { radius, height } => 1.0 / 3.0 * PI * radius * radius * height,
             Cube { side } => side * side * side,
             Tetrahedron { side } => side * side * side / 6.0 * 2.0_f64.sqrt(),
+            Pyramid { base, height } => base * base * height / 3.0,
+        }
+    }
+
+    fn dimensions(&self) -> Vec<(&'static str, f64)> {
+        use ThreeDShape::*;
+        match self {
+            Sphere { radius } => vec![("radius", *radius)],
+            Cilinder { radius, height } | Cone { radius, height } => {
+                vec![("radius", *radius), ("height", *height)]
+            }
+            Cube { side } | Tetrahedron { side } => vec![("side", *side)],
+            Pyramid { base, height } => vec![("base", *base), ("height", *height)],
         }
     }
 
@@ -86,10 +140,15 @@ impl ThreeDShape {
         use ThreeDShape::*;
         match self {
             Sphere { radius } => 4.0 * PI * radius * radius,
-            Cilinder { radius, height } => 2.0 * PI * radius * height + 2.0 * PI * radius * radius,
+            Cilinder { radius, height } => 2.0 * PI * radius * (radius + height),
             Cone { radius, height } => PI * radius * (radius + (radius.powi(2) + height.powi(2)).sqrt()),
             Cube { side } => 6.0 * side * side,
             Tetrahedron { side } => 3.0_f64.sqrt() * side * side,
+            // The square base plus four triangles whose height is the slant height.
+            Pyramid { base, height } => {
+                let slant = (height * height + base * base / 4.0).sqrt();
+                base * base + 2.0 * base * slant
+            }
         }
     }
 }
@@ -98,6 +157,12 @@ impl ThreeDShape {
 pub enum ErrorKind {
     NotA2DShape,
     NotA3DShape,
+    ZeroVolume,
+    InvalidShape(String),
+    ExpectedTwoShapes(usize),
+    InvalidTriangle,
+    NonPositiveDimension(&'static str),
+    MissingDimension(&'static str),
 }
 
 impl std::fmt::Display for ErrorKind {
@@ -106,16 +171,41 @@ impl std::fmt::Display for ErrorKind {
         match self {
             NotA2DShape => write!(f, "Not a 2D shape"),
             NotA3DShape => write!(f, "Not a 3D shape"),
+            ZeroVolume => write!(f, "Shape has zero volume"),
+            InvalidShape(e) => write!(f, "Invalid shape: {}", e),
+            ExpectedTwoShapes(n) => write!(f, "Expected two shapes, got {}", n),
+            InvalidTriangle => write!(
+                f,
+                "Invalid triangle: sides must be positive and each shorter than the other two combined"
+            ),
+            NonPositiveDimension(name) => write!(f, "The {} must be a positive number", name),
+            MissingDimension(name) => write!(f, "Missing {}", name),
         }
     }
 }
 
+// Infinite and NaN dimensions are rejected along with zero and negative ones.
+fn check_dimensions(dimensions: &[(&'static str, f64)]) -> Result<(), ErrorKind> {
+    for &(name, value) in dimensions {
+        if !value.is_finite() || value <= 0.0 {
+            return Err(ErrorKind::NonPositiveDimension(name));
+        }
+    }
+    Ok(())
+}
+
 impl Shape {
     pub fn area(&self) -> Result<f64, ErrorKind> {
         use Shape::*;
         match self {
-            TwoD(s) => Ok(s.area()),
-            ThreeD(s) => Ok(s.surface_area()),
+            TwoD(s) => {
+                check_dimensions(&s.area_dimensions())?;
+                s.area()
+            }
+            ThreeD(s) => {
+                check_dimensions(&s.dimensions())?;
+                Ok(s.surface_area())
+            }
         }
     }
 
@@ -123,7 +213,10 @@ impl Shape {
         use ErrorKind::*;
         use Shape::*;
         match self {
-            TwoD(s) => Ok(s.perimeter()),
+            TwoD(s) => {
+                check_dimensions(&s.perimeter_dimensions())?;
+                s.perimeter()
+            }
             ThreeD(_) => Err(NotA2DShape),
         }
     }
@@ -133,7 +226,154 @@ impl Shape {
         use Shape::*;
         match self {
             TwoD(_) => Err(NotA3DShape),
-            ThreeD(s) => Ok(s.volume()),
+            ThreeD(s) => {
+                check_dimensions(&s.dimensions())?;
+                Ok(s.volume())
+            }
+        }
+    }
+
+    pub fn surface_area(&self) -> Result<f64, ErrorKind> {
+        use ErrorKind::*;
+        use Shape::*;
+        match self {
+            TwoD(_) => Err(NotA3DShape),
+            ThreeD(s) => {
+                check_dimensions(&s.dimensions())?;
+                Ok(s.surface_area())
+            }
+        }
+    }
+
+    pub fn surface_to_volume_ratio(&self) -> Result<f64, ErrorKind> {
+        let volume = self.volume()?;
+        if volume == 0.0 {
+            return Err(ErrorKind::ZeroVolume);
+        }
+        Ok(self.area()? / volume)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sphere_ratio_is_three_over_radius() {
+        let radius = 3.0;
+        let sphere = Shape::ThreeD(ThreeDShape::Sphere { radius });
+        let ratio = sphere.surface_to_volume_ratio().unwrap();
+        assert!((ratio - 3.0 / radius).abs() < 1e-12);
+    }
+
+    fn triangle(base: f64, side2: f64, side3: f64) -> Shape {
+        Shape::TwoD(TwoDShape::Triangle {
+            base,
+            height: 0.0,
+            side2,
+            side3,
+        })
+    }
+
+    #[test]
+    fn test_triangle_inequality() {
+        assert_eq!(triangle(3.0, 4.0, 5.0).perimeter().unwrap(), 12.0);
+        for (a, b, c) in [
+            (1.0, 1.0, 100.0),
+            (100.0, 1.0, 1.0),
+            (1.0, 100.0, 1.0),
+            (1.0, 1.0, 2.0),
+        ] {
+            assert!(matches!(
+                triangle(a, b, c).perimeter(),
+                Err(ErrorKind::InvalidTriangle)
+            ));
+        }
+        assert!(matches!(
+            triangle(-3.0, 4.0, 5.0).perimeter(),
+            Err(ErrorKind::NonPositiveDimension("side1"))
+        ));
+
+        let flat = TwoDShape::Triangle {
+            base: 2.0,
+            height: 0.0,
+            side2: 0.0,
+            side3: 0.0,
+        };
+        assert!(matches!(flat.area(), Err(ErrorKind::InvalidTriangle)));
+    }
+
+    #[test]
+    fn test_ratio_of_degenerate_shape_errors() {
+        // Positive, but small enough for the volume to underflow to zero.
+        let cube = Shape::ThreeD(ThreeDShape::Cube { side: 1e-200 });
+        assert!(matches!(cube.surface_to_volume_ratio(), Err(ErrorKind::ZeroVolume)));
+    }
+
+    #[test]
+    fn test_pyramid() {
+        let pyramid = Shape::ThreeD(ThreeDShape::Pyramid {
+            base: 6.0,
+            height: 4.0,
+        });
+        assert!((pyramid.volume().unwrap() - 48.0).abs() < 1e-12);
+        // Slant height 5, so each face is 15.
+        assert!((pyramid.area().unwrap() - (36.0 + 4.0 * 15.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_non_positive_dimensions() {
+        use ErrorKind::NonPositiveDimension;
+        let two_d = [
+            (TwoDShape::Square { side: 0.0 }, "side"),
+            (TwoDShape::Circle { radius: -1.0 }, "radius"),
+            (
+                TwoDShape::Triangle {
+                    base: 2.0,
+                    height: -1.0,
+                    side2: 0.0,
+                    side3: 0.0,
+                },
+                "height",
+            ),
+            (
+                TwoDShape::Rectangle {
+                    width: 0.0,
+                    height: 2.0,
+                },
+                "width",
+            ),
+        ];
+        for (shape, field) in two_d {
+            let shape = Shape::TwoD(shape);
+            assert!(matches!(shape.area(), Err(NonPositiveDimension(f)) if f == field));
+            assert!(matches!(shape.perimeter(), Err(NonPositiveDimension(_))));
+        }
+
+        let three_d = [
+            (ThreeDShape::Sphere { radius: 0.0 }, "radius"),
+            (
+                ThreeDShape::Cilinder {
+                    radius: 1.0,
+                    height: -2.0,
+                },
+                "height",
+            ),
+            (
+                ThreeDShape::Cone {
+                    radius: -1.0,
+                    height: 2.0,
+                },
+                "radius",
+            ),
+            (ThreeDShape::Cube { side: -5.0 }, "side"),
+            (ThreeDShape::Tetrahedron { side: f64::NAN }, "side"),
+        ];
+        for (shape, field) in three_d {
+            let shape = Shape::ThreeD(shape);
+            assert!(matches!(shape.area(), Err(NonPositiveDimension(f)) if f == field));
+            assert!(matches!(shape.volume(), Err(NonPositiveDimension(f)) if f == field));
+            assert!(matches!(shape.surface_area(), Err(NonPositiveDimension(_))));
         }
     }
 }
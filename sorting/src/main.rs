@@ -1,13 +1,19 @@
 use rand::Rng;
 use std::{
+    cmp::Ordering,
     fmt::{self, Debug, Display},
+    env, fs,
     io::{self, stdin, stdout, Write},
 };
 
 enum ErrorKind {
     Io(io::Error),
     ParseInt,
+    Parse(String),
+    UnknownElementType(String),
     EmptyInput,
+    ColumnOutOfRange(usize),
+    Usage,
 }
 
 impl ErrorKind {
@@ -15,7 +21,14 @@ impl ErrorKind {
         match self {
             ErrorKind::Io(e) => format!("I/O error: {}", e),
             ErrorKind::ParseInt => "Failed to parse an integer".to_string(),
+            ErrorKind::Parse(s) => format!("Failed to parse {}", s),
+            ErrorKind::UnknownElementType(s) => format!("Unknown element type {}", s),
             ErrorKind::EmptyInput => "Empty input".to_string(),
+            ErrorKind::ColumnOutOfRange(col) => format!("No row has a column {}", col),
+            ErrorKind::Usage => {
+                "Usage: quicksort [--descending] [--csv | <csv_file> <column> [--numeric]]"
+                    .to_string()
+            }
         }
     }
 }
@@ -32,11 +45,19 @@ impl From<io::Error> for ErrorKind {
     }
 }
 
-fn partition<T: Ord>(v: &mut [T], low: usize, high: usize, pivot: usize) -> usize {
+type Cmp<T> = fn(&T, &T) -> Ordering;
+
+fn partition<T, F: Fn(&T, &T) -> Ordering>(
+    v: &mut [T],
+    low: usize,
+    high: usize,
+    pivot: usize,
+    cmp: &F,
+) -> usize {
     v.swap(pivot, high);
     let mut store_index = low;
     for i in low..high {
-        if v[i] < v[high] {
+        if cmp(&v[i], &v[high]).is_lt() {
             v.swap(i, store_index);
             store_index += 1;
         }
@@ -45,42 +66,124 @@ fn partition<T: Ord>(v: &mut [T], low: usize, high: usize, pivot: usize) -> usiz
     store_index
 }
 
-fn quicksort_base<T: Ord>(v: &mut [T], low: usize, high: usize) {
-    if low < high {
-        let pivot = (low + high) / 2;
-        let pivot_index = partition(v, low, high, pivot);
+// Ranges shorter than this are left to insertion sort.
+const INSERTION_THRESHOLD: usize = 16;
+
+// Index of the median of v[low], v[mid] and v[high], so already sorted and
+// reversed ranges still split near the middle.
+fn median_of_three<T, F: Fn(&T, &T) -> Ordering>(
+    v: &[T],
+    low: usize,
+    high: usize,
+    cmp: &F,
+) -> usize {
+    let mid = (low + high) / 2;
+    let (a, b, c) = (&v[low], &v[mid], &v[high]);
+    if cmp(a, b).is_lt() {
+        if cmp(b, c).is_lt() {
+            mid
+        } else if cmp(a, c).is_lt() {
+            high
+        } else {
+            low
+        }
+    } else if cmp(a, c).is_lt() {
+        low
+    } else if cmp(b, c).is_lt() {
+        high
+    } else {
+        mid
+    }
+}
+
+// Returns how many calls were made, so the partition schemes can be compared.
+fn quicksort_base<T, F: Fn(&T, &T) -> Ordering>(
+    v: &mut [T],
+    low: usize,
+    high: usize,
+    cmp: &F,
+) -> usize {
+    let mut calls = 1;
+    if low < high && high - low < INSERTION_THRESHOLD {
+        insertion_sort(&mut v[low..=high], cmp);
+    } else if low < high {
+        let pivot = median_of_three(v, low, high, cmp);
+        let pivot_index = partition(v, low, high, pivot, cmp);
         if pivot_index > 0 {
-            quicksort_base(v, low, pivot_index - 1);
+            calls += quicksort_base(v, low, pivot_index - 1, cmp);
         }
-        quicksort_base(v, pivot + 1, high);
+        calls += quicksort_base(v, pivot_index + 1, high, cmp);
     }
+    calls
 }
 
-fn quicksort<T: Ord>(v: &mut [T]) {
+fn quicksort<T, F: Fn(&T, &T) -> Ordering>(v: &mut [T], cmp: &F) {
     let len = v.len();
     if v.len() <= 1 {
         return;
     }
-    quicksort_base(v, 0, len - 1);
+    quicksort_base(v, 0, len - 1, cmp);
+}
+
+// Splits v into elements smaller than, equal to and greater than the pivot,
+// so runs of equal keys are never partitioned again.
+fn partition_3way<T, F: Fn(&T, &T) -> Ordering>(
+    v: &mut [T],
+    pivot: usize,
+    cmp: &F,
+) -> (usize, usize) {
+    v.swap(0, pivot);
+    let mut lt = 0;
+    let mut i = 1;
+    let mut gt = v.len();
+    while i < gt {
+        match cmp(&v[i], &v[lt]) {
+            Ordering::Less => {
+                v.swap(lt, i);
+                lt += 1;
+                i += 1;
+            }
+            Ordering::Greater => {
+                gt -= 1;
+                v.swap(i, gt);
+            }
+            Ordering::Equal => i += 1,
+        }
+    }
+    (lt, gt)
 }
 
-fn bubble_sort<T: Ord>(v: &mut [T]) {
+fn quicksort_3way_base<T, F: Fn(&T, &T) -> Ordering>(v: &mut [T], cmp: &F) -> usize {
+    if v.len() <= 1 {
+        return 1;
+    }
+    let (lt, gt) = partition_3way(v, v.len() / 2, cmp);
+    let (smaller, rest) = v.split_at_mut(lt);
+    let greater = &mut rest[gt - lt..];
+    1 + quicksort_3way_base(smaller, cmp) + quicksort_3way_base(greater, cmp)
+}
+
+fn quicksort_3way<T, F: Fn(&T, &T) -> Ordering>(v: &mut [T], cmp: &F) {
+    quicksort_3way_base(v, cmp);
+}
+
+fn bubble_sort<T, F: Fn(&T, &T) -> Ordering>(v: &mut [T], cmp: &F) {
     let len = v.len();
     for i in 0..len {
         for j in 0..len - i - 1 {
-            if v[j] > v[j + 1] {
+            if cmp(&v[j], &v[j + 1]).is_gt() {
                 v.swap(j, j + 1);
             }
         }
     }
 }
 
-fn selection_sort<T: Ord>(v: &mut [T]) {
+fn selection_sort<T, F: Fn(&T, &T) -> Ordering>(v: &mut [T], cmp: &F) {
     let len = v.len();
     for i in 0..len {
         let mut min = i;
         for j in i + 1..len {
-            if v[j] < v[min] {
+            if cmp(&v[j], &v[min]).is_lt() {
                 min = j;
             }
         }
@@ -88,15 +191,21 @@ fn selection_sort<T: Ord>(v: &mut [T]) {
     }
 }
 
-fn merge_sort<T: Ord + Clone>(v: &mut [T]) {
-    fn merge<T: Ord + Clone>(v: &mut [T], low: usize, mid: usize, high: usize) {
+fn merge_sort<T: Clone, F: Fn(&T, &T) -> Ordering>(v: &mut [T], cmp: &F) {
+    fn merge<T: Clone, F: Fn(&T, &T) -> Ordering>(
+        v: &mut [T],
+        low: usize,
+        mid: usize,
+        high: usize,
+        cmp: &F,
+    ) {
         let left = v[low..mid].to_vec();
         let right = v[mid..high].to_vec();
         let mut i = 0;
         let mut j = 0;
         let mut k = low;
         while i < left.len() && j < right.len() {
-            if left[i] <= right[j] {
+            if cmp(&left[i], &right[j]).is_le() {
                 v[k] = left[i].clone();
                 i += 1;
             } else {
@@ -117,30 +226,210 @@ fn merge_sort<T: Ord + Clone>(v: &mut [T]) {
         }
     }
 
-    fn merge_sort_base<T: Ord + Clone>(v: &mut [T], low: usize, high: usize) {
-        if low < high - 1 {
+    // Ranges of zero or one element are already sorted.
+    fn merge_sort_base<T: Clone, F: Fn(&T, &T) -> Ordering>(
+        v: &mut [T],
+        low: usize,
+        high: usize,
+        cmp: &F,
+    ) {
+        if low + 1 < high {
             let mid = (low + high) / 2;
-            merge_sort_base(v, low, mid);
-            merge_sort_base(v, mid, high);
-            merge(v, low, mid, high);
+            merge_sort_base(v, low, mid, cmp);
+            merge_sort_base(v, mid, high, cmp);
+            merge(v, low, mid, high, cmp);
         }
     }
 
     let len = v.len();
-    merge_sort_base(v, 0, len);
+    merge_sort_base(v, 0, len, cmp);
+}
+
+// Same algorithm as merge_sort, but every merge goes through one scratch
+// buffer allocated up front instead of two new vectors per merge.
+fn merge_sort_buffered<T: Clone, F: Fn(&T, &T) -> Ordering>(v: &mut [T], cmp: &F) {
+    fn merge<T: Clone, F: Fn(&T, &T) -> Ordering>(
+        v: &mut [T],
+        buffer: &mut [T],
+        low: usize,
+        mid: usize,
+        high: usize,
+        cmp: &F,
+    ) {
+        buffer[low..high].clone_from_slice(&v[low..high]);
+        let mut i = low;
+        let mut j = mid;
+        let mut k = low;
+        while i < mid && j < high {
+            if cmp(&buffer[i], &buffer[j]).is_le() {
+                v[k] = buffer[i].clone();
+                i += 1;
+            } else {
+                v[k] = buffer[j].clone();
+                j += 1;
+            }
+            k += 1;
+        }
+        while i < mid {
+            v[k] = buffer[i].clone();
+            i += 1;
+            k += 1;
+        }
+        while j < high {
+            v[k] = buffer[j].clone();
+            j += 1;
+            k += 1;
+        }
+    }
+
+    fn merge_sort_base<T: Clone, F: Fn(&T, &T) -> Ordering>(
+        v: &mut [T],
+        buffer: &mut [T],
+        low: usize,
+        high: usize,
+        cmp: &F,
+    ) {
+        if low + 1 < high {
+            let mid = (low + high) / 2;
+            merge_sort_base(v, buffer, low, mid, cmp);
+            merge_sort_base(v, buffer, mid, high, cmp);
+            merge(v, buffer, low, mid, high, cmp);
+        }
+    }
+
+    let mut buffer = v.to_vec();
+    let len = v.len();
+    merge_sort_base(v, &mut buffer, 0, len, cmp);
+}
+
+// Ranges wider than this would need too large a count table.
+const MAX_COUNTING_RANGE: u64 = 1 << 24;
+
+fn counting_sort(v: &mut [i32]) {
+    let (min, max) = match (v.iter().min(), v.iter().max()) {
+        (Some(&min), Some(&max)) => (min, max),
+        _ => return,
+    };
+    let range = (max as i64 - min as i64) as u64 + 1;
+    if range > MAX_COUNTING_RANGE {
+        eprintln!(
+            "Warning: range of {} values is too wide for counting sort, using merge sort",
+            range
+        );
+        merge_sort_buffered(v, &i32::cmp);
+        return;
+    }
+    let mut counts = vec![0usize; range as usize];
+    for &x in v.iter() {
+        counts[(x as i64 - min as i64) as usize] += 1;
+    }
+    let mut k = 0;
+    for (offset, &count) in counts.iter().enumerate() {
+        for _ in 0..count {
+            v[k] = (min as i64 + offset as i64) as i32;
+            k += 1;
+        }
+    }
+}
+
+// Spreads the values over one bucket per element, which keeps buckets small
+// when the input is uniformly distributed, then insertion sorts each bucket.
+fn bucket_sort(v: &mut [i32]) {
+    let (min, max) = match (v.iter().min(), v.iter().max()) {
+        (Some(&min), Some(&max)) => (min as i64, max as i64),
+        _ => return,
+    };
+    let len = v.len();
+    let range = max - min + 1;
+    let mut buckets: Vec<Vec<i32>> = vec![Vec::new(); len];
+    for &x in v.iter() {
+        let index = ((x as i64 - min) as i128 * len as i128 / range as i128) as usize;
+        buckets[index].push(x);
+    }
+    let mut k = 0;
+    for mut bucket in buckets {
+        insertion_sort(&mut bucket, &i32::cmp);
+        for x in bucket {
+            v[k] = x;
+            k += 1;
+        }
+    }
+}
+
+// LSD radix sort on bytes, four passes for the four bytes of an i32. Flipping
+// the sign bit maps i32::MIN..=i32::MAX onto 0..=u32::MAX in the same order.
+fn radix_sort(v: &mut [i32]) {
+    let mut keys: Vec<u32> = v.iter().map(|&x| x as u32 ^ 0x8000_0000).collect();
+    let mut buffer = vec![0u32; keys.len()];
+    for pass in 0..4 {
+        let shift = pass * 8;
+        let mut counts = [0usize; 257];
+        for &key in &keys {
+            counts[((key >> shift) & 0xff) as usize + 1] += 1;
+        }
+        for i in 1..257 {
+            counts[i] += counts[i - 1];
+        }
+        for &key in &keys {
+            let digit = ((key >> shift) & 0xff) as usize;
+            buffer[counts[digit]] = key;
+            counts[digit] += 1;
+        }
+        std::mem::swap(&mut keys, &mut buffer);
+    }
+    for (x, key) in v.iter_mut().zip(keys) {
+        *x = (key ^ 0x8000_0000) as i32;
+    }
 }
 
-fn insertion_sort<T: Ord>(v: &mut [T]) {
+fn insertion_sort<T, F: Fn(&T, &T) -> Ordering>(v: &mut [T], cmp: &F) {
     let len = v.len();
     for i in 1..len {
         let mut j = i;
-        while j > 0 && v[j] < v[j - 1] {
+        while j > 0 && cmp(&v[j], &v[j - 1]).is_lt() {
             v.swap(j, j - 1);
             j -= 1;
         }
     }
 }
 
+// Cells missing from shorter rows sort first. In numeric mode cells that
+// parse as numbers come before the ones that don't, which compare as text.
+fn compare_cells(a: Option<&String>, b: Option<&String>, numeric: bool) -> Ordering {
+    match (a, b) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Less,
+        (Some(_), None) => Ordering::Greater,
+        (Some(a), Some(b)) if numeric => {
+            match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+                (Ok(x), Ok(y)) => x.total_cmp(&y),
+                (Ok(_), Err(_)) => Ordering::Less,
+                (Err(_), Ok(_)) => Ordering::Greater,
+                (Err(_), Err(_)) => a.cmp(b),
+            }
+        }
+        (Some(a), Some(b)) => a.cmp(b),
+    }
+}
+
+// Descending order only flips rows that both have the column, so missing
+// cells still come first and ties keep their order.
+fn sort_rows(
+    rows: &mut [Vec<String>],
+    col: usize,
+    numeric: bool,
+    descending: bool,
+) -> Result<(), ErrorKind> {
+    if !rows.iter().any(|row| col < row.len()) {
+        return Err(ErrorKind::ColumnOutOfRange(col));
+    }
+    rows.sort_by(|a, b| match (a.get(col), b.get(col)) {
+        (Some(x), Some(y)) if descending => compare_cells(Some(y), Some(x), numeric),
+        (x, y) => compare_cells(x, y, numeric),
+    });
+    Ok(())
+}
+
 mod tests {
     #[cfg(test)]
     use super::*;
@@ -159,16 +448,70 @@ mod tests {
         let mut nums = generate_random_sequence();
         let mut v1 = nums.clone();
         v1.sort();
-        quicksort(&mut nums);
+        quicksort(&mut nums, &Ord::cmp);
         assert_eq!(nums, v1);
     }
 
+    #[test]
+    fn test_int_quicksort_3way() {
+        let mut nums = generate_random_sequence();
+        let mut v1 = nums.clone();
+        v1.sort();
+        quicksort_3way(&mut nums, &Ord::cmp);
+        assert_eq!(nums, v1);
+    }
+
+    #[test]
+    fn test_quicksort_on_sorted_and_reversed() {
+        let sorted: Vec<i32> = (0..10_000).collect();
+        let mut nums = sorted.clone();
+        quicksort(&mut nums, &Ord::cmp);
+        assert_eq!(nums, sorted);
+
+        let mut reversed: Vec<i32> = (0..10_000).rev().collect();
+        quicksort(&mut reversed, &Ord::cmp);
+        assert_eq!(reversed, sorted);
+
+        // Organ pipe: ascending then descending.
+        let mut pipe: Vec<i32> = (0..5_000).chain((0..5_000).rev()).collect();
+        let mut expected = pipe.clone();
+        expected.sort();
+        quicksort(&mut pipe, &Ord::cmp);
+        assert_eq!(pipe, expected);
+    }
+
+    #[test]
+    fn test_median_of_three() {
+        for perm in permutations(&[0, 1, 2]) {
+            let v = vec![perm[0], 7, 7, perm[1], 7, 7, perm[2]];
+            let median = median_of_three(&v, 0, v.len() - 1, &i32::cmp);
+            assert_eq!(v[median], 1, "failed on {:?}", perm);
+        }
+    }
+
+    #[test]
+    fn test_quicksort_3way_on_duplicates() {
+        let mut rng = rand::thread_rng();
+        let nums: Vec<i32> = (0..1000).map(|_| rng.gen_range(0..3)).collect();
+        let mut expected = nums.clone();
+        expected.sort();
+
+        let mut two_way = nums.clone();
+        let two_way_calls = quicksort_base(&mut two_way, 0, nums.len() - 1, &i32::cmp);
+        let mut three_way = nums.clone();
+        let three_way_calls = quicksort_3way_base(&mut three_way, &i32::cmp);
+
+        assert_eq!(two_way, expected);
+        assert_eq!(three_way, expected);
+        assert!(three_way_calls * 10 < two_way_calls);
+    }
+
     #[test]
     fn test_int_bubblesort() {
         let mut nums = generate_random_sequence();
         let mut v1 = nums.clone();
         v1.sort();
-        bubble_sort(&mut nums);
+        bubble_sort(&mut nums, &Ord::cmp);
         assert_eq!(nums, v1);
     }
 
@@ -177,7 +520,7 @@ mod tests {
         let mut nums = generate_random_sequence();
         let mut v1 = nums.clone();
         v1.sort();
-        selection_sort(&mut nums);
+        selection_sort(&mut nums, &Ord::cmp);
         assert_eq!(nums, v1);
     }
 
@@ -186,7 +529,7 @@ mod tests {
         let mut nums = generate_random_sequence();
         let mut v1 = nums.clone();
         v1.sort();
-        insertion_sort(&mut nums);
+        insertion_sort(&mut nums, &Ord::cmp);
         assert_eq!(nums, v1);
     }
 
@@ -195,66 +538,528 @@ mod tests {
         let mut nums = generate_random_sequence();
         let mut v1 = nums.clone();
         v1.sort();
-        merge_sort(&mut nums);
+        merge_sort(&mut nums, &Ord::cmp);
+        assert_eq!(nums, v1);
+    }
+
+    #[allow(dead_code)]
+    fn permutations(v: &[i32]) -> Vec<Vec<i32>> {
+        if v.len() <= 1 {
+            return vec![v.to_vec()];
+        }
+        let mut result = Vec::new();
+        for i in 0..v.len() {
+            let mut rest = v.to_vec();
+            let first = rest.remove(i);
+            for mut perm in permutations(&rest) {
+                perm.insert(0, first);
+                result.push(perm);
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn test_mergesort_small_inputs() {
+        let mut empty: Vec<i32> = Vec::new();
+        merge_sort(&mut empty, &Ord::cmp);
+        assert!(empty.is_empty());
+
+        let mut one = vec![1];
+        merge_sort(&mut one, &Ord::cmp);
+        assert_eq!(one, vec![1]);
+
+        for perm in permutations(&[0, 1]) {
+            let mut nums = perm.clone();
+            merge_sort(&mut nums, &Ord::cmp);
+            assert_eq!(nums, vec![0, 1], "failed on {:?}", perm);
+        }
+
+        let perms = permutations(&[0, 1, 2, 3]);
+        assert_eq!(perms.len(), 24);
+        for perm in perms {
+            let mut nums = perm.clone();
+            merge_sort(&mut nums, &Ord::cmp);
+            assert_eq!(nums, vec![0, 1, 2, 3], "failed on {:?}", perm);
+        }
+    }
+
+    #[test]
+    fn test_int_mergesort_buffered() {
+        let mut nums = generate_random_sequence();
+        let mut v1 = nums.clone();
+        v1.sort();
+        merge_sort_buffered(&mut nums, &Ord::cmp);
+        assert_eq!(nums, v1);
+
+        let mut empty: Vec<i32> = Vec::new();
+        merge_sort_buffered(&mut empty, &Ord::cmp);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_mergesort_buffered_large_input() {
+        use rand::prelude::*;
+        let mut nums: Vec<i32> = (0..100_000).collect();
+        nums.shuffle(&mut rand::thread_rng());
+        merge_sort_buffered(&mut nums, &Ord::cmp);
+        assert!(nums.iter().copied().eq(0..100_000));
+    }
+
+    #[test]
+    fn test_mergesort_buffered_is_stable() {
+        // Sorting by the first element only must keep the second in order.
+        #[derive(Clone, Debug, PartialEq, Eq)]
+        struct Pair(i32, usize);
+        impl PartialOrd for Pair {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+        impl Ord for Pair {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.0.cmp(&other.0)
+            }
+        }
+
+        let mut pairs: Vec<Pair> = (0..200).map(|i| Pair((i * 7 % 5) as i32, i)).collect();
+        merge_sort_buffered(&mut pairs, &Ord::cmp);
+        for w in pairs.windows(2) {
+            assert!(w[0].0 < w[1].0 || (w[0].0 == w[1].0 && w[0].1 < w[1].1));
+        }
+    }
+
+    #[test]
+    fn test_descending_sorts() {
+        let nums = generate_random_sequence();
+        let mut expected = nums.clone();
+        expected.sort_by(|a, b| b.cmp(a));
+        type Sort = fn(&mut [i32], &Cmp<i32>);
+        let descending: Cmp<i32> = |a, b| b.cmp(a);
+        let sorts: [(&str, Sort); 7] = [
+            ("bubble", bubble_sort),
+            ("selection", selection_sort),
+            ("insertion", insertion_sort),
+            ("merge", merge_sort),
+            ("buffered merge", merge_sort_buffered),
+            ("quick", quicksort),
+            ("3-way quick", quicksort_3way),
+        ];
+        for (name, sort) in sorts {
+            let mut v = nums.clone();
+            sort(&mut v, &descending);
+            assert_eq!(v, expected, "{} sort", name);
+        }
+    }
+
+    // Keys repeat every five elements, the second field is the original index.
+    #[allow(dead_code)]
+    fn keyed_sequence() -> Vec<(i32, usize)> {
+        (0..200).map(|i| ((i * 7 % 5) as i32, i)).collect()
+    }
+
+    #[allow(dead_code)]
+    fn is_stable(v: &[(i32, usize)]) -> bool {
+        v.windows(2)
+            .all(|w| w[0].0 < w[1].0 || (w[0].0 == w[1].0 && w[0].1 < w[1].1))
+    }
+
+    #[test]
+    fn test_sort_stability() {
+        type Sort = fn(&mut [(i32, usize)], &Cmp<(i32, usize)>);
+        let by_key: Cmp<(i32, usize)> = |a, b| a.0.cmp(&b.0);
+        let stable: [(&str, Sort); 4] = [
+            ("bubble", bubble_sort),
+            ("insertion", insertion_sort),
+            ("merge", merge_sort),
+            ("buffered merge", merge_sort_buffered),
+        ];
+        let unstable: [(&str, Sort); 3] = [
+            ("selection", selection_sort),
+            ("quick", quicksort),
+            ("3-way quick", quicksort_3way),
+        ];
+        for (name, sort) in stable {
+            let mut v = keyed_sequence();
+            sort(&mut v, &by_key);
+            assert!(is_stable(&v), "{} sort should be stable", name);
+        }
+        for (name, sort) in unstable {
+            let mut v = keyed_sequence();
+            sort(&mut v, &by_key);
+            assert!(v.windows(2).all(|w| w[0].0 <= w[1].0), "{} sort", name);
+            assert!(!is_stable(&v), "{} sort should not be stable", name);
+        }
+    }
+
+    #[test]
+    fn test_floats_and_words() {
+        let floats: Vec<f64> = read_vec("2.5 -1 1e2 0.25").unwrap_or_default();
+        let words: Vec<String> = read_vec("pear apple fig").unwrap_or_default();
+        assert!(matches!(read_vec::<f64>("1.5 x"), Err(ErrorKind::Parse(s)) if s == "x"));
+
+        for (name, sort) in comparison_sorts(f64::total_cmp) {
+            let mut v = floats.clone();
+            sort(&mut v);
+            assert_eq!(v, vec![-1.0, 0.25, 2.5, 100.0], "{}", name);
+        }
+        for (name, sort) in comparison_sorts(String::cmp) {
+            let mut v = words.clone();
+            sort(&mut v);
+            assert_eq!(v, vec!["apple", "fig", "pear"], "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_int_countingsort() {
+        let mut nums = generate_random_sequence();
+        let mut v1 = nums.clone();
+        v1.sort();
+        counting_sort(&mut nums);
+        assert_eq!(nums, v1);
+
+        let mut tight = vec![3, -1, 3, 2, -1, 0, 3];
+        counting_sort(&mut tight);
+        assert_eq!(tight, vec![-1, -1, 0, 2, 3, 3, 3]);
+
+        let mut wide = vec![i32::MAX, 0, i32::MIN, -5];
+        counting_sort(&mut wide);
+        assert_eq!(wide, vec![i32::MIN, -5, 0, i32::MAX]);
+    }
+
+    #[test]
+    fn test_int_bucketsort() {
+        let mut nums = generate_random_sequence();
+        let mut v1 = nums.clone();
+        v1.sort();
+        bucket_sort(&mut nums);
+        assert_eq!(nums, v1);
+
+        let mut tight = vec![7, 7, -7, 0, 7, -7];
+        bucket_sort(&mut tight);
+        assert_eq!(tight, vec![-7, -7, 0, 7, 7, 7]);
+
+        let mut wide = vec![i32::MAX, 0, i32::MIN, -5];
+        bucket_sort(&mut wide);
+        assert_eq!(wide, vec![i32::MIN, -5, 0, i32::MAX]);
+    }
+
+    #[allow(dead_code)]
+    fn rows(data: &[&[&str]]) -> Vec<Vec<String>> {
+        data.iter()
+            .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_int_radixsort() {
+        let mut nums = generate_random_sequence();
+        let mut v1 = nums.clone();
+        v1.sort();
+        radix_sort(&mut nums);
         assert_eq!(nums, v1);
+
+        let mut extremes = vec![i32::MAX, 0, -1, i32::MIN, 256, -256, 1];
+        radix_sort(&mut extremes);
+        assert_eq!(extremes, vec![i32::MIN, -256, -1, 0, 1, 256, i32::MAX]);
+    }
+
+    #[test]
+    fn test_sort_rows_by_numeric_column() {
+        let mut data = rows(&[&["b", "10"], &["a", "9"], &["c"], &["d", "x"], &["e", "-1.5"]]);
+        assert!(sort_rows(&mut data, 1, true, false).is_ok());
+        let order: Vec<&str> = data.iter().map(|row| row[0].as_str()).collect();
+        assert_eq!(order, vec!["c", "e", "a", "b", "d"]);
+    }
+
+    #[test]
+    fn test_sort_rows_by_text_column() {
+        let mut data = rows(&[&["pear", "10"], &["apple"], &["fig", "9"]]);
+        assert!(sort_rows(&mut data, 0, false, false).is_ok());
+        let order: Vec<&str> = data.iter().map(|row| row[0].as_str()).collect();
+        assert_eq!(order, vec!["apple", "fig", "pear"]);
+
+        assert!(sort_rows(&mut data, 1, false, false).is_ok());
+        assert_eq!(data[0], vec!["apple"]);
+        assert_eq!(data[1][1], "10");
+
+        assert!(matches!(
+            sort_rows(&mut data, 2, false, false),
+            Err(ErrorKind::ColumnOutOfRange(2))
+        ));
+    }
+
+    #[test]
+    fn test_sort_rows_descending_keeps_ties_and_missing_cells_in_place() {
+        let mut data = rows(&[&["a", "1"], &["b"], &["c", "2"], &["d", "1"], &["e"]]);
+        assert!(sort_rows(&mut data, 1, true, true).is_ok());
+        let order: Vec<&str> = data.iter().map(|row| row[0].as_str()).collect();
+        assert_eq!(order, vec!["b", "e", "c", "a", "d"]);
+    }
+
+    #[test]
+    fn test_csv_timings_has_a_line_per_algorithm_and_size() {
+        let mut out = Vec::new();
+        assert!(write_csv_timings(&mut out, false, &[5, 20]).is_ok());
+        let out = String::from_utf8(out).unwrap();
+        let mut lines = out.lines();
+        assert_eq!(lines.next(), Some("algorithm,size,nanos"));
+
+        let runs: Vec<(String, String)> = lines
+            .map(|line| {
+                let cells: Vec<&str> = line.split(',').collect();
+                assert_eq!(cells.len(), 3);
+                assert!(cells[2].parse::<u128>().is_ok());
+                (cells[0].to_string(), cells[1].to_string())
+            })
+            .collect();
+        let names: Vec<&str> = algorithms(false).iter().map(|(name, _)| *name).collect();
+        let expected: Vec<(String, String)> = ["5", "20"]
+            .iter()
+            .flat_map(|size| {
+                names
+                    .iter()
+                    .map(|name| (name.to_string(), size.to_string()))
+            })
+            .collect();
+        assert_eq!(runs, expected);
     }
 }
 
-fn read_vec<T: Ord + std::str::FromStr>(vec: &str) -> Result<Vec<T>, ErrorKind> {
+fn read_vec<T: std::str::FromStr>(vec: &str) -> Result<Vec<T>, ErrorKind> {
     if vec.is_empty() {
         return Err(ErrorKind::EmptyInput);
     }
     vec.split_whitespace()
-        .map(|s| s.parse::<T>().map_err(|_| ErrorKind::ParseInt))
+        .map(|s| s.parse::<T>().map_err(|_| ErrorKind::Parse(s.to_string())))
         .collect()
 }
 
-fn prompt() -> Result<Vec<i32>, ErrorKind> {
-    print!("Generate a random vector of integers or input one separated by spaces: ");
+fn random_vec(len: usize) -> Vec<i32> {
+    let mut rng = rand::thread_rng();
+    (0..len).map(|_| rng.gen_range(-100..100)).collect()
+}
+
+fn random_float_vec(len: usize) -> Vec<f64> {
+    let mut rng = rand::thread_rng();
+    (0..len).map(|_| rng.gen_range(-100.0..100.0)).collect()
+}
+
+enum Input {
+    Integers(Vec<i32>),
+    Floats(Vec<f64>),
+    Words(Vec<String>),
+}
+
+fn read_prompt(msg: &str) -> Result<String, ErrorKind> {
+    print!("{}", msg);
     stdout().flush().unwrap();
     let mut buf = String::new();
     stdin().read_line(&mut buf).map_err(ErrorKind::Io)?;
-    match read_vec::<i32>(&buf) {
-        Ok(v) => {
-            if v.len() == 1 {
-                let mut rng = rand::thread_rng();
-                let mut nums = Vec::new();
+    Ok(buf)
+}
 
-                for _ in 0..v[0] {
-                    nums.push(rng.gen_range(-100..100));
-                }
-                Ok(nums)
+// A single number is taken as the length of a random vector, which only
+// makes sense for the numeric types.
+fn prompt() -> Result<Input, ErrorKind> {
+    let kind = read_prompt("Sort integers, floats or words? (i/f/w): ")?;
+    match kind.trim() {
+        "i" | "" => {
+            let buf = read_prompt(
+                "Generate a random vector of integers or input one separated by spaces: ",
+            )?;
+            let v = read_vec::<i32>(&buf)?;
+            if v.len() == 1 {
+                Ok(Input::Integers(random_vec(v[0].max(0) as usize)))
             } else {
-                Ok(v)
+                Ok(Input::Integers(v))
+            }
+        }
+        "f" => {
+            let buf = read_prompt(
+                "Generate a random vector of floats or input one separated by spaces: ",
+            )?;
+            match buf.trim().parse::<usize>() {
+                Ok(len) => Ok(Input::Floats(random_float_vec(len))),
+                Err(_) => Ok(Input::Floats(read_vec::<f64>(&buf)?)),
             }
         }
-        Err(e) => Err(e),
+        "w" => {
+            let buf = read_prompt("Input words separated by spaces: ")?;
+            Ok(Input::Words(read_vec::<String>(&buf)?))
+        }
+        other => Err(ErrorKind::UnknownElementType(other.to_string())),
     }
 }
 
-fn time<T: Ord + Debug>(
+fn measure<T>(v: &mut [T], func: &dyn Fn(&mut [T])) -> std::time::Duration {
+    let start = std::time::Instant::now();
+    func(v);
+    start.elapsed()
+}
+
+fn time<T: Debug>(
     v: &mut Vec<T>,
     func: &dyn Fn(&mut [T]),
     func_name: &str,
 ) -> std::time::Duration {
-    let start = std::time::Instant::now();
-    func(v);
-    let elapsed = start.elapsed();
+    let elapsed = measure(v, func);
     println!("Sorted: {:?} in {:?} by {}", v, elapsed, func_name);
     elapsed
 }
 
+fn sort_csv(args: &[String], descending: bool) -> Result<(), ErrorKind> {
+    let (path, col) = match args {
+        [path, col] | [path, col, _] => (path, col),
+        _ => return Err(ErrorKind::Usage),
+    };
+    let col = col.parse::<usize>().map_err(|_| ErrorKind::ParseInt)?;
+    let numeric = match args.get(2).map(String::as_str) {
+        Some("--numeric") => true,
+        Some(_) => return Err(ErrorKind::Usage),
+        None => false,
+    };
+    let content = fs::read_to_string(path)?;
+    let mut rows: Vec<Vec<String>> = content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.split(',').map(|cell| cell.trim().to_string()).collect())
+        .collect();
+    sort_rows(&mut rows, col, numeric, descending)?;
+    for row in rows {
+        println!("{}", row.join(","));
+    }
+    Ok(())
+}
+
+type Algorithm<T> = (&'static str, Box<dyn Fn(&mut [T])>);
+
+fn comparison_sorts<T: Clone + 'static>(order: Cmp<T>) -> Vec<Algorithm<T>> {
+    vec![
+        ("Bubble Sort", Box::new(move |v| bubble_sort(v, &order))),
+        (
+            "Insertion Sort",
+            Box::new(move |v| insertion_sort(v, &order)),
+        ),
+        (
+            "Selection Sort",
+            Box::new(move |v| selection_sort(v, &order)),
+        ),
+        ("Merge Sort", Box::new(move |v| merge_sort(v, &order))),
+        (
+            "Buffered Merge Sort",
+            Box::new(move |v| merge_sort_buffered(v, &order)),
+        ),
+        ("Quick Sort", Box::new(move |v| quicksort(v, &order))),
+        (
+            "3-way Quick Sort",
+            Box::new(move |v| quicksort_3way(v, &order)),
+        ),
+    ]
+}
+
+fn algorithms(descending: bool) -> Vec<Algorithm<i32>> {
+    let order: Cmp<i32> = if descending {
+        |a, b| b.cmp(a)
+    } else {
+        i32::cmp
+    };
+    // Counting, bucket and radix sort never compare elements, so their
+    // output is reversed instead.
+    let reversed = move |sort: fn(&mut [i32])| {
+        Box::new(move |v: &mut [i32]| {
+            sort(v);
+            if descending {
+                v.reverse();
+            }
+        })
+    };
+    let mut algorithms = comparison_sorts(order);
+    algorithms.push(("Counting Sort", reversed(counting_sort)));
+    algorithms.push(("Bucket Sort", reversed(bucket_sort)));
+    algorithms.push(("Radix Sort", reversed(radix_sort)));
+    algorithms
+}
+
+fn time_all<T: Clone + Debug>(v: Vec<T>, algorithms: Vec<Algorithm<T>>) {
+    let timings: Vec<(&str, std::time::Duration)> = algorithms
+        .iter()
+        .map(|(name, func)| (*name, time(&mut v.clone(), func.as_ref(), name)))
+        .collect();
+    println!("Timings:");
+    for (name, elapsed) in timings {
+        println!("{}: {:?}", name, elapsed);
+    }
+}
+
+const CSV_SIZES: [usize; 3] = [100, 1000, 10000];
+
+// Writes one `algorithm,size,nanos` line per run, for plotting.
+fn write_csv_timings<W: Write>(
+    out: &mut W,
+    descending: bool,
+    sizes: &[usize],
+) -> Result<(), ErrorKind> {
+    let algorithms = algorithms(descending);
+    writeln!(out, "algorithm,size,nanos")?;
+    for &size in sizes {
+        let v = random_vec(size);
+        for (name, func) in &algorithms {
+            let elapsed = measure(&mut v.clone(), func.as_ref());
+            writeln!(out, "{},{},{}", name, size, elapsed.as_nanos())?;
+        }
+    }
+    Ok(())
+}
+
+fn take_flag(args: &mut Vec<String>, names: &[&str]) -> bool {
+    match args.iter().position(|arg| names.contains(&arg.as_str())) {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    }
+}
+
 fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let descending = take_flag(&mut args, &["--descending", "-r"]);
+    if take_flag(&mut args, &["--csv"]) {
+        let result = if args.is_empty() {
+            write_csv_timings(&mut stdout(), descending, &CSV_SIZES)
+        } else {
+            Err(ErrorKind::Usage)
+        };
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+        }
+        return;
+    }
+    if !args.is_empty() {
+        if let Err(e) = sort_csv(&args, descending) {
+            eprintln!("Error: {}", e);
+        }
+        return;
+    }
+
     match prompt() {
-        Ok(v) => {
-            let bubble_time = time(&mut v.clone(), &bubble_sort, "Bubble Sort");
-            let insertion_time = time(&mut v.clone(), &insertion_sort, "Insertion Sort");
-            let selection_time = time(&mut v.clone(), &selection_sort, "Selection Sort");
-            let merge_time = time(&mut v.clone(), &merge_sort, "Merge Sort");
-            let quick_time = time(&mut v.clone(), &quicksort, "Quick Sort");
-            println!("Timings:\nBubble Sort: {:?}\nSelection Sort: {:?}\nInsertion Sort: {:?}\nQuick Sort: {:?}\nMerge Sort: {:?}",
-                bubble_time, selection_time, insertion_time, quick_time, merge_time
-            );
+        Ok(Input::Integers(v)) => time_all(v, algorithms(descending)),
+        Ok(Input::Floats(v)) => {
+            let order: Cmp<f64> = if descending {
+                |a, b| b.total_cmp(a)
+            } else {
+                f64::total_cmp
+            };
+            time_all(v, comparison_sorts(order));
+        }
+        Ok(Input::Words(v)) => {
+            let order: Cmp<String> = if descending {
+                |a, b| b.cmp(a)
+            } else {
+                String::cmp
+            };
+            time_all(v, comparison_sorts(order));
         }
         Err(e) => eprintln!("Error: {}", e),
     }
@@ -1,4 +1,4 @@
-use std::any::Any;
+use std::{any::Any, fmt};
 
 struct Stack {
     elements: Vec<Box<dyn Any>>,
@@ -30,6 +30,83 @@ impl Stack {
     fn is_empty(&self) -> bool {
         self.elements.is_empty()
     }
+
+    // Starts from the top of the stack, the element pop would return first.
+    fn iter(&self) -> impl Iterator<Item = &dyn Any> {
+        self.elements.iter().rev().map(|element| element.as_ref())
+    }
+}
+
+// Names the concrete type of the element along with its value, for the
+// types the demo pushes.
+fn describe(element: &dyn Any) -> String {
+    if let Some(value) = element.downcast_ref::<i32>() {
+        format!("i32: {}", value)
+    } else if let Some(value) = element.downcast_ref::<f64>() {
+        format!("f64: {}", value)
+    } else if let Some(value) = element.downcast_ref::<&str>() {
+        format!("&str: {}", value)
+    } else if let Some(value) = element.downcast_ref::<Vec<i32>>() {
+        format!("Vec<i32>: {:?}", value)
+    } else if let Some(value) = element.downcast_ref::<String>() {
+        format!("String: {}", value)
+    } else {
+        "unknown type".to_string()
+    }
+}
+
+impl fmt::Display for Stack {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let elements: Vec<String> = self.iter().map(describe).collect();
+        write!(f, "[{}]", elements.join(", "))
+    }
+}
+
+impl fmt::Debug for Stack {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Stack {}", self)
+    }
+}
+
+// Holds a single element type, so nothing needs to be downcast. Prefer it
+// over Stack unless the elements really are of different types.
+struct TypedStack<T> {
+    elements: Vec<T>,
+}
+
+impl<T> TypedStack<T> {
+    fn new() -> Self {
+        TypedStack {
+            elements: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, item: T) {
+        self.elements.push(item);
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        self.elements.pop()
+    }
+
+    fn peek(&self) -> Option<&T> {
+        self.elements.last()
+    }
+
+    fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for TypedStack<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let elements: Vec<String> = self.elements.iter().rev().map(|e| e.to_string()).collect();
+        write!(f, "[{}]", elements.join(", "))
+    }
 }
 
 fn main() {
@@ -42,36 +119,82 @@ fn main() {
     stack.push(String::from("Rust"));
 
     println!("Stack length: {}", stack.len());
+    println!("Stack: {}", stack);
 
-    if let Some(top) = stack.peek() {
-        if let Some(value) = top.downcast_ref::<i32>() {
-            println!("Top element is an i32: {}", value);
-        } else if let Some(value) = top.downcast_ref::<f64>() {
-            println!("Top element is an f64: {}", value);
-        } else if let Some(value) = top.downcast_ref::<String>() {
-            println!("Top element is a String: {}", value);
-        } else {
-            println!("Top element is of an unknown type");
-        }
-    } else {
-        println!("The stack is empty");
+    match stack.peek() {
+        Some(top) => println!("Top element is {}", describe(top.as_ref())),
+        None => println!("The stack is empty"),
     }
 
     while let Some(top) = stack.pop() {
-        if let Some(value) = top.downcast_ref::<i32>() {
-            println!("Popping i32: {}", value);
-        } else if let Some(value) = top.downcast_ref::<f64>() {
-            println!("Popping f64: {}", value);
-        } else if let Some(value) = top.downcast_ref::<&str>() {
-            println!("Popping &str: {}", value);
-        } else if let Some(value) = top.downcast_ref::<Vec<i32>>() {
-            println!("Popping Vec<i32>: {:?}", value);
-        } else if let Some(value) = top.downcast_ref::<String>() {
-            println!("Popping String: {}", value);
-        } else {
-            println!("Unknown type");
-        }
+        println!("Popping {}", describe(top.as_ref()));
     }
 
     println!("Stack is empty: {}", stack.is_empty());
+
+    let mut words = TypedStack::new();
+    for word in ["typed", "stacks", "need", "no", "downcasting"] {
+        words.push(word.to_string());
+    }
+
+    println!("Typed stack length: {}", words.len());
+    println!("Typed stack: {}", words);
+    if let Some(top) = words.peek() {
+        println!("Top word: {}", top);
+    }
+    while let Some(word) = words.pop() {
+        println!("Popping word: {}", word);
+    }
+
+    println!("Typed stack is empty: {}", words.is_empty());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_typed_stack_integers() {
+        let mut stack = TypedStack::new();
+        assert!(stack.is_empty());
+        assert_eq!(stack.pop(), None);
+        for n in 1..=3 {
+            stack.push(n);
+        }
+        assert_eq!(stack.len(), 3);
+        assert_eq!(stack.peek(), Some(&3));
+        assert_eq!(stack.pop(), Some(3));
+        assert_eq!(stack.pop(), Some(2));
+        assert_eq!(stack.len(), 1);
+    }
+
+    #[test]
+    fn test_typed_stack_strings() {
+        let mut stack = TypedStack::new();
+        stack.push(String::from("Hello"));
+        stack.push(String::from("Rust"));
+        assert_eq!(stack.peek().map(String::as_str), Some("Rust"));
+        assert_eq!(stack.pop(), Some(String::from("Rust")));
+        assert_eq!(stack.pop(), Some(String::from("Hello")));
+        assert_eq!(stack.peek(), None);
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn test_iter_is_lifo() {
+        let mut stack = Stack::new();
+        stack.push(1);
+        stack.push("two");
+        stack.push(String::from("three"));
+        let described: Vec<String> = stack.iter().map(describe).collect();
+        assert_eq!(described, vec!["String: three", "&str: two", "i32: 1"]);
+        assert_eq!(stack.to_string(), "[String: three, &str: two, i32: 1]");
+        assert_eq!(stack.len(), 3);
+
+        let mut typed = TypedStack::new();
+        for n in 1..=3 {
+            typed.push(n);
+        }
+        assert_eq!(typed.to_string(), "[3, 2, 1]");
+    }
 }
@@ -1,27 +1,72 @@
-use std::io::{prelude::*, stdin, stdout};
+use std::{
+    env,
+    io::{self, prelude::*, stdin, stdout},
+    process,
+};
+
+// The operations edit_string understands, which are the ones that need no
+// arguments.
+const EDIT_OPERATIONS: [&str; 6] = [
+    "reverse",
+    "capitalize",
+    "lowercase",
+    "switch_case",
+    "sort_chars",
+    "sort_words",
+];
 
 fn edit_string(string: &mut String, operation: &str) {
-    let tmp: String;
-    match operation {
-        "reverse" => tmp = string.chars().rev().collect(),
-        "capitalize" => tmp = string.to_uppercase(),
-        "lowercase" => tmp = string.to_lowercase(),
-        "switch_case" => tmp = string.chars().map(|c| {
+    let tmp: String = match operation {
+        "reverse" => string.chars().rev().collect(),
+        "capitalize" => string.to_uppercase(),
+        "lowercase" => string.to_lowercase(),
+        "switch_case" => string.chars().map(|c| {
             if c.is_uppercase() {
                 c.to_lowercase().next().unwrap()
             } else {
                 c.to_uppercase().next().unwrap()
             }
         }).collect(),
+        // Both sorts compare code points, so case matters and every uppercase
+        // letter comes before every lowercase one. Duplicates are all kept.
+        // Words are joined back with single spaces.
+        "sort_chars" => {
+            let mut chars: Vec<char> = string.chars().collect();
+            chars.sort();
+            chars.into_iter().collect()
+        }
+        "sort_words" => {
+            let mut words: Vec<&str> = string.split_whitespace().collect();
+            words.sort();
+            words.join(" ")
+        }
         _ => {
             eprintln!("Invalid operation");
             return;
         }
-    }
+    };
     string.clear();
     string.push_str(&tmp);
 }
 
+// Fewest single character insertions, deletions and substitutions that turn
+// one string into the other, keeping only the previous row of the table.
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, &b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != b_char);
+            let deletion = previous[j + 1] + 1;
+            let insertion = current[j] + 1;
+            current.push(substitution.min(deletion).min(insertion));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
 fn change_string(string: &mut String, operation: &str, args: Option<Vec<String>>) {
     match operation {
         "change_letter" => {
@@ -53,19 +98,81 @@ fn change_string(string: &mut String, operation: &str, args: Option<Vec<String>>
                 }
             }
             string.clear();
-            string.push_str(&new_string.trim());
+            string.push_str(new_string.trim());
+        }
+        // Equal characters or words are identical, so reversing the ascending
+        // order gives the descending one.
+        "sort_chars" | "sort_words" => {
+            edit_string(string, operation);
+            let descending = matches!(
+                args.as_deref(),
+                Some([flag, ..]) if flag == "desc" || flag == "descending"
+            );
+            if descending {
+                let reversed: String = match operation {
+                    "sort_chars" => string.chars().rev().collect(),
+                    _ => string.split(' ').rev().collect::<Vec<&str>>().join(" "),
+                };
+                string.clear();
+                string.push_str(&reversed);
+            }
+        }
+        // The arguments are joined back with single spaces, so the other
+        // string can have several words.
+        "distance" => {
+            let other = args.unwrap_or_default().join(" ");
+            println!("Distance: {}", levenshtein(string, &other));
         }
         _ => println!("Invalid operation"),
     }
 }
 
+// Applies the operation to every line of input, writing one line of output
+// for each.
+fn transform_lines(
+    input: impl BufRead,
+    output: &mut impl Write,
+    operation: &str,
+) -> io::Result<()> {
+    for line in input.lines() {
+        let mut line = line?;
+        edit_string(&mut line, operation);
+        writeln!(output, "{}", line)?;
+    }
+    output.flush()
+}
+
+// With an operation as argument, transforms stdin line by line instead of
+// prompting, e.g. `cat names.txt | string_edit capitalize`.
+fn run_stream_mode(operation: &str) {
+    if !EDIT_OPERATIONS.contains(&operation) {
+        eprintln!(
+            "Unknown operation: {} (expected one of {})",
+            operation,
+            EDIT_OPERATIONS.join(", ")
+        );
+        process::exit(1);
+    }
+    if let Err(e) = transform_lines(stdin().lock(), &mut stdout().lock(), operation) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
+
 fn main() {
+    if let Some(operation) = env::args().nth(1) {
+        run_stream_mode(&operation);
+        return;
+    }
     loop {
         let mut string_input = String::new();
         print!("Enter string: ");
         stdout().flush().unwrap();
-        stdin().read_line(&mut string_input)
+        let read = stdin().read_line(&mut string_input)
             .expect("Failed to read line");
+        if read == 0 {
+            break;
+        }
         let mut string = string_input.trim().to_string();
         let mut operation_input = String::new();
         print!("Enter operation: ");
@@ -73,13 +180,13 @@ fn main() {
         stdin().read_line(&mut operation_input)
             .expect("Failed to read line");
         let operation = operation_input.trim();
-        if &operation[..2] == "ch" {
+        if operation.starts_with("ch") || operation.starts_with("sort") || operation == "distance" {
             print!("Enter arguments: ");
             stdout().flush().unwrap();
             let mut args_input = String::new();
             stdin().read_line(&mut args_input)
                 .expect("Failed to read line");
-            let args = args_input.trim().split_whitespace().map(|s| s.to_string()).collect();
+            let args = args_input.split_whitespace().map(|s| s.to_string()).collect();
             change_string(&mut string, operation, Some(args));
         } else {
             edit_string(&mut string, operation);
@@ -87,3 +194,67 @@ fn main() {
         println!("{}", string);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transform_lines_reverse() {
+        let input = "hello\nrust exercises\n\nabc";
+        let mut output = Vec::new();
+        transform_lines(input.as_bytes(), &mut output, "reverse").unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "olleh\nsesicrexe tsur\n\ncba\n"
+        );
+    }
+
+    fn edited(string: &str, operation: &str) -> String {
+        let mut string = string.to_string();
+        edit_string(&mut string, operation);
+        string
+    }
+
+    fn changed(string: &str, operation: &str, args: &[&str]) -> String {
+        let mut string = string.to_string();
+        let args = args.iter().map(|arg| arg.to_string()).collect();
+        change_string(&mut string, operation, Some(args));
+        string
+    }
+
+    #[test]
+    fn test_sort_chars() {
+        assert_eq!(edited("dcba", "sort_chars"), "abcd");
+        assert_eq!(edited("bAab", "sort_chars"), "Aabb");
+        assert_eq!(changed("dcba", "sort_chars", &[]), "abcd");
+        assert_eq!(changed("adcb", "sort_chars", &["desc"]), "dcba");
+    }
+
+    #[test]
+    fn test_sort_words() {
+        assert_eq!(
+            edited("banana apple cherry", "sort_words"),
+            "apple banana cherry"
+        );
+        assert_eq!(
+            edited("  pear Apple  pear ", "sort_words"),
+            "Apple pear pear"
+        );
+        assert_eq!(
+            changed("banana apple cherry", "sort_words", &["descending"]),
+            "cherry banana apple"
+        );
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("sitting", "kitten"), 3);
+        assert_eq!(levenshtein("flaw", "flaw"), 0);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("abcd", ""), 4);
+        assert_eq!(levenshtein("", ""), 0);
+        assert_eq!(levenshtein("café", "cafe"), 1);
+    }
+}
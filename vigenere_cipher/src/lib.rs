@@ -0,0 +1,295 @@
+// Keeps its place in the key between calls, so a text can be enciphered in
+// chunks and give the same result as in one go.
+pub struct Cipher {
+    shifts: Vec<u8>,
+    position: usize,
+    encrypt: bool,
+}
+
+impl Cipher {
+    // Only the key's ASCII letters count, whatever their case. Returns None
+    // when the key has no letters.
+    pub fn new(key: &str, encrypt: bool) -> Option<Cipher> {
+        let shifts = letter_indices(key);
+        if shifts.is_empty() {
+            return None;
+        }
+        Some(Cipher {
+            shifts,
+            position: 0,
+            encrypt,
+        })
+    }
+
+    // Letters outside a..z and A..Z are copied as they are and don't use up
+    // a key letter.
+    pub fn apply(&mut self, text: &str) -> String {
+        let mut result = String::new();
+        for c in text.chars() {
+            let new_char = if c.is_ascii_alphabetic() {
+                let shift = self.shifts[self.position];
+                self.position = (self.position + 1) % self.shifts.len();
+                let base = if c.is_ascii_uppercase() { b'A' } else { b'a' };
+                (shift_letter(c as u8 - base, shift, self.encrypt) + base) as char
+            } else {
+                c
+            };
+            result.push(new_char);
+        }
+        result
+    }
+}
+
+// Shifts a letter given as 0..26 forwards when encrypting and backwards when
+// decrypting.
+fn shift_letter(letter: u8, shift: u8, encrypt: bool) -> u8 {
+    if encrypt {
+        (letter + shift) % 26
+    } else {
+        (letter + 26 - shift) % 26
+    }
+}
+
+fn letter_indices(text: &str) -> Vec<u8> {
+    text.chars()
+        .filter(char::is_ascii_alphabetic)
+        .map(|c| c.to_ascii_lowercase() as u8 - b'a')
+        .collect()
+}
+
+// Like vigenere_cipher, but once the key runs out the plaintext itself
+// continues the keystream, so the key never repeats. Decryption has to feed
+// every letter it recovers back in before it can go on to the next one.
+pub fn autokey_cipher(text: &str, key: &str, encrypt: bool) -> Option<String> {
+    let mut keystream = letter_indices(key);
+    if keystream.is_empty() {
+        return None;
+    }
+    let mut position = 0;
+    let mut result = String::new();
+    for c in text.chars() {
+        if !c.is_ascii_alphabetic() {
+            result.push(c);
+            continue;
+        }
+        let base = if c.is_ascii_uppercase() { b'A' } else { b'a' };
+        let letter = c as u8 - base;
+        let shifted = shift_letter(letter, keystream[position], encrypt);
+        position += 1;
+        keystream.push(if encrypt { letter } else { shifted });
+        result.push((shifted + base) as char);
+    }
+    Some(result)
+}
+
+// Lines the crib up with the ciphertext at every possible offset and returns
+// the key letters that would turn the crib into the ciphertext there. Offsets
+// count letters only, as anything else doesn't use up the key.
+pub fn recover_key_fragment(cipher: &str, crib: &str) -> Vec<(usize, String)> {
+    let cipher = letter_indices(cipher);
+    let crib = letter_indices(crib);
+    if crib.is_empty() || crib.len() > cipher.len() {
+        return Vec::new();
+    }
+    (0..=cipher.len() - crib.len())
+        .map(|offset| {
+            let key = cipher[offset..]
+                .iter()
+                .zip(&crib)
+                .map(|(&c, &p)| (shift_letter(c, p, false) + b'a') as char)
+                .collect();
+            (offset, key)
+        })
+        .collect()
+}
+
+pub fn vigenere_cipher(text: &str, key: &str, encrypt: bool) -> Option<String> {
+    Cipher::new(key, encrypt).map(|mut cipher| cipher.apply(text))
+}
+
+// Relative frequency of each letter a..z in English text, in percent.
+const ENGLISH_FREQUENCIES: [f64; 26] = [
+    8.17, 1.49, 2.78, 4.25, 12.70, 2.23, 2.02, 6.09, 6.97, 0.15, 0.77, 4.03, 2.41, 6.75, 7.51,
+    1.93, 0.10, 5.99, 6.33, 9.06, 2.76, 0.98, 2.36, 0.15, 1.97, 0.07,
+];
+
+// Chi-squared distance between the text's letter counts and the counts
+// English would give for the same number of letters. Lower is more English.
+fn english_score(text: &str) -> f64 {
+    let letters = letter_indices(text);
+    let mut counts = [0usize; 26];
+    for &letter in &letters {
+        counts[letter as usize] += 1;
+    }
+    counts
+        .iter()
+        .zip(ENGLISH_FREQUENCIES)
+        .map(|(&count, frequency)| {
+            let expected = frequency / 100.0 * letters.len() as f64;
+            (count as f64 - expected).powi(2) / expected
+        })
+        .sum()
+}
+
+// Tries every Caesar shift and returns the shift, the decryption and its
+// score, best scoring first.
+pub fn crack_caesar(ciphertext: &str) -> Vec<(i32, String, f64)> {
+    let mut candidates: Vec<(i32, String, f64)> = (0..26u8)
+        .map(|shift| {
+            let key = ((b'a' + shift) as char).to_string();
+            let plaintext = vigenere_cipher(ciphertext, &key, false).unwrap_or_default();
+            let score = english_score(&plaintext);
+            (shift as i32, plaintext, score)
+        })
+        .collect();
+    candidates.sort_by(|a, b| a.2.total_cmp(&b.2).then(a.0.cmp(&b.0)));
+    candidates
+}
+
+#[derive(Debug, PartialEq)]
+pub enum KeyError {
+    Empty,
+    NotInAlphabet(char),
+}
+
+impl std::fmt::Display for KeyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            KeyError::Empty => write!(f, "The key is empty"),
+            KeyError::NotInAlphabet(c) => {
+                write!(f, "The key character {:?} is not in the alphabet", c)
+            }
+        }
+    }
+}
+
+// Shifts within the given alphabet instead of a..z. Matching is exact, so
+// with a lowercase alphabet uppercase letters are copied as they are, like
+// every other character outside it. The key may only use the alphabet.
+pub fn vigenere_with_alphabet(
+    text: &str,
+    key: &str,
+    alphabet: &[char],
+    encrypt: bool,
+) -> Result<String, KeyError> {
+    let index_of = |c: char| alphabet.iter().position(|&a| a == c);
+    let shifts = key
+        .chars()
+        .map(|c| index_of(c).ok_or(KeyError::NotInAlphabet(c)))
+        .collect::<Result<Vec<usize>, KeyError>>()?;
+    if shifts.is_empty() {
+        return Err(KeyError::Empty);
+    }
+    let size = alphabet.len();
+    let mut position = 0;
+    let result = text
+        .chars()
+        .map(|c| match index_of(c) {
+            Some(index) => {
+                let shift = shifts[position % shifts.len()];
+                position += 1;
+                match encrypt {
+                    true => alphabet[(index + shift) % size],
+                    false => alphabet[(index + size - shift) % size],
+                }
+            }
+            None => c,
+        })
+        .collect();
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_skips_punctuation() {
+        let encrypted = vigenere_cipher("Hello, World!", "key", true).unwrap();
+        assert_eq!(encrypted, "Rijvs, Uyvjn!");
+        assert_eq!(
+            vigenere_cipher(&encrypted, "key", false).unwrap(),
+            "Hello, World!"
+        );
+    }
+
+    #[test]
+    fn test_key_is_normalized() {
+        let expected = vigenere_cipher("Hello, World!", "key", true);
+        assert_eq!(vigenere_cipher("Hello, World!", "K e-Y", true), expected);
+        assert_eq!(
+            vigenere_cipher("café au lait", "b", true).unwrap(),
+            "dbgé bv mbju"
+        );
+        assert_eq!(vigenere_cipher("text", " 12!", true), None);
+    }
+
+    #[test]
+    fn test_chunks_continue_the_key() {
+        let mut cipher = Cipher::new("key", true).unwrap();
+        let chunked = cipher.apply("Hello,\n") + &cipher.apply("World!\n");
+        assert_eq!(
+            Some(chunked),
+            vigenere_cipher("Hello,\nWorld!\n", "key", true)
+        );
+    }
+
+    #[test]
+    fn test_recover_key_fragment() {
+        let cipher = vigenere_cipher("Attack at dawn, then retreat!", "lemon", true).unwrap();
+        let candidates = recover_key_fragment(&cipher, "at dawn");
+        assert_eq!(candidates.len(), 23 - 6 + 1);
+        assert_eq!(candidates[6], (6, "emonle".to_string()));
+        assert!(candidates.iter().all(|(_, key)| key.len() == 6));
+        assert!(recover_key_fragment(&cipher, "").is_empty());
+        assert!(recover_key_fragment("abc", "abcd").is_empty());
+    }
+
+    #[test]
+    fn test_autokey_round_trip() {
+        let encrypted = autokey_cipher("attackatdawn", "queenly", true).unwrap();
+        assert_eq!(encrypted, "qnxepvytwtwp");
+        assert_eq!(
+            autokey_cipher(&encrypted, "queenly", false).unwrap(),
+            "attackatdawn"
+        );
+
+        let text = "Meet me at the Old Bridge, 9 o'clock!";
+        let encrypted = autokey_cipher(text, "Key", true).unwrap();
+        assert_ne!(encrypted, vigenere_cipher(text, "Key", true).unwrap());
+        assert_eq!(autokey_cipher(&encrypted, "Key", false).unwrap(), text);
+        assert_eq!(autokey_cipher(text, "123", true), None);
+    }
+
+    #[test]
+    fn test_alphanumeric_alphabet() {
+        let alphabet: Vec<char> = "abcdefghijklmnopqrstuvwxyz0123456789".chars().collect();
+        let text = "Order 66 at 9pm, room b12!";
+        let encrypted = vigenere_with_alphabet(text, "k3y", &alphabet, true).unwrap();
+        assert_eq!(encrypted, "O1621 zu km xzf, fyha luq!");
+        assert_eq!(
+            vigenere_with_alphabet(&encrypted, "k3y", &alphabet, false).unwrap(),
+            text
+        );
+        assert_eq!(
+            vigenere_with_alphabet(text, "K3y", &alphabet, true),
+            Err(KeyError::NotInAlphabet('K'))
+        );
+        assert_eq!(
+            vigenere_with_alphabet(text, "", &alphabet, true),
+            Err(KeyError::Empty)
+        );
+    }
+
+    #[test]
+    fn test_crack_caesar_ranks_the_shift_first() {
+        let text =
+            "The quick brown fox jumps over the lazy dog, then naps in the warm afternoon sun.";
+        let cipher = vigenere_cipher(text, "h", true).unwrap();
+        let candidates = crack_caesar(&cipher);
+        assert_eq!(candidates.len(), 26);
+        assert_eq!(candidates[0].0, 7);
+        assert_eq!(candidates[0].1, text);
+        assert!(candidates.windows(2).all(|pair| pair[0].2 <= pair[1].2));
+    }
+}
@@ -1,27 +1,129 @@
-use std::io::{prelude::*, stdin, stdout};
-
-fn vigenere_cipher(text: &str, key: &str, encrypt: bool) -> String {
-    let mut result = String::new();
-    let mut key_iter = key.chars().cycle();
-    for (_i, c) in text.chars().enumerate() {
-        let shift = key_iter.next().unwrap() as u8 - 'a' as u8;
-        let new_char = if c.is_ascii_alphabetic() {
-            let base = if c.is_uppercase() { 'A' } else { 'a' } as u8;
-            let new_char = if encrypt {
-                (((c as u8 - base + shift) % 26) + base) as char
-            } else {
-                (((c as u8 - base + 26 - shift) % 26) + base) as char
-            };
-            new_char
-        } else {
-            c
-        };
-        result.push(new_char);
-    }
-    result
+use std::{
+    env,
+    fs::File,
+    io::{self, prelude::*, stdin, stdout, BufReader, BufWriter},
+};
+use vigenere_cipher::{crack_caesar, recover_key_fragment, vigenere_cipher, Cipher};
+
+struct FileArgs {
+    input: String,
+    output: String,
+    key: String,
+    encrypt: bool,
+}
+
+const USAGE: &str = "Usage: vigenere_cipher --in <path> --out <path> --key <key> --encrypt|--decrypt
+       vigenere_cipher --crib <known plaintext> (reads the ciphertext from stdin)
+       vigenere_cipher --crack (reads a Caesar ciphertext from stdin)";
+
+fn parse_args(args: &[String]) -> Result<FileArgs, &'static str> {
+    let (mut input, mut output, mut key, mut encrypt) = (None, None, None, None);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--in" => input = args.next().cloned(),
+            "--out" => output = args.next().cloned(),
+            "--key" => key = args.next().cloned(),
+            "--encrypt" if encrypt.is_none() => encrypt = Some(true),
+            "--decrypt" if encrypt.is_none() => encrypt = Some(false),
+            _ => return Err(USAGE),
+        }
+    }
+    match (input, output, key, encrypt) {
+        (Some(input), Some(output), Some(key), Some(encrypt)) => Ok(FileArgs {
+            input,
+            output,
+            key,
+            encrypt,
+        }),
+        _ => Err(USAGE),
+    }
+}
+
+// Enciphers the input a line at a time, keeping the line endings.
+fn process_file(input: &str, output: &str, cipher: &mut Cipher) -> io::Result<()> {
+    let mut reader = BufReader::new(File::open(input)?);
+    let mut writer = BufWriter::new(File::create(output)?);
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 {
+        writer.write_all(cipher.apply(&line).as_bytes())?;
+        line.clear();
+    }
+    writer.flush()
+}
+
+fn run_file_mode(args: &[String]) {
+    let args = match parse_args(args) {
+        Ok(args) => args,
+        Err(usage) => {
+            eprintln!("{}", usage);
+            return;
+        }
+    };
+    let mut cipher = match Cipher::new(&args.key, args.encrypt) {
+        Some(cipher) => cipher,
+        None => {
+            eprintln!("The key must contain at least one letter");
+            return;
+        }
+    };
+    if let Err(e) = process_file(&args.input, &args.output, &mut cipher) {
+        eprintln!("Error: {}", e);
+    }
+}
+
+// Reads the ciphertext from stdin and prints the key letters the crib implies
+// at each offset.
+fn run_crib_mode(crib: &str) {
+    let mut cipher = String::new();
+    if let Err(e) = stdin().read_to_string(&mut cipher) {
+        eprintln!("Error: {}", e);
+        return;
+    }
+    let candidates = recover_key_fragment(&cipher, crib);
+    if candidates.is_empty() {
+        println!("The crib has no letters or is longer than the ciphertext");
+    }
+    for (offset, key) in candidates {
+        println!("{}: {}", offset, key);
+    }
+}
+
+// Reads a Caesar ciphertext from stdin and prints the three most English
+// looking shifts.
+fn run_crack_mode() {
+    let mut cipher = String::new();
+    if let Err(e) = stdin().read_to_string(&mut cipher) {
+        eprintln!("Error: {}", e);
+        return;
+    }
+    for (shift, plaintext, score) in crack_caesar(&cipher).into_iter().take(3) {
+        println!(
+            "Shift {} (score {:.1}): {}",
+            shift,
+            score,
+            plaintext.trim_end()
+        );
+    }
 }
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args == ["--crack"] {
+        run_crack_mode();
+        return;
+    }
+    if let [flag, crib] = &args[..] {
+        if flag == "--crib" {
+            run_crib_mode(crib);
+            return;
+        }
+    }
+    if !args.is_empty() {
+        run_file_mode(&args);
+        return;
+    }
+
     let mut text = String::new();
     let mut key = String::new();
     print!("Enter text: ");
@@ -39,9 +141,64 @@ fn main() {
     let mut choice = String::new();
     stdin().read_line(&mut choice).unwrap();
     choice = choice.trim().to_string();
-    match choice.as_str() {
-        "E" => println!("Encrypted text: {}", vigenere_cipher(&text, &key, true)),
-        "D" => println!("Decrypted text: {}", vigenere_cipher(&text, &key, false)),
-        _ => println!("Invalid choice"),
+    let encrypt = match choice.as_str() {
+        "E" => true,
+        "D" => false,
+        _ => {
+            println!("Invalid choice");
+            return;
+        }
+    };
+    match vigenere_cipher(&text, &key, encrypt) {
+        Some(result) if encrypt => println!("Encrypted text: {}", result),
+        Some(result) => println!("Decrypted text: {}", result),
+        None => println!("The key must contain at least one letter"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let parsed = parse_args(&args("--key key --in a.txt --decrypt --out b.txt")).unwrap();
+        assert_eq!(parsed.input, "a.txt");
+        assert_eq!(parsed.output, "b.txt");
+        assert_eq!(parsed.key, "key");
+        assert!(!parsed.encrypt);
+        assert!(parse_args(&args("--in a.txt --out b.txt --key key")).is_err());
+        let both = args("--in a.txt --out b.txt --key key --encrypt --decrypt");
+        assert!(parse_args(&both).is_err());
+        assert!(parse_args(&args("--in a.txt --out b.txt --encrypt --key")).is_err());
+    }
+
+    #[test]
+    fn test_process_file_round_trip() {
+        let dir = env::temp_dir();
+        let id = std::process::id();
+        let plain = dir.join(format!("vigenere-plain-{}.txt", id));
+        let encrypted = dir.join(format!("vigenere-encrypted-{}.txt", id));
+        let decrypted = dir.join(format!("vigenere-decrypted-{}.txt", id));
+        let text = "Hello, World!\nAttack at dawn.\n";
+        std::fs::write(&plain, text).unwrap();
+
+        let path = |p: &std::path::PathBuf| p.to_str().unwrap().to_string();
+        let mut cipher = Cipher::new("key", true).unwrap();
+        process_file(&path(&plain), &path(&encrypted), &mut cipher).unwrap();
+        let mut cipher = Cipher::new("key", false).unwrap();
+        process_file(&path(&encrypted), &path(&decrypted), &mut cipher).unwrap();
+
+        let encrypted_text = std::fs::read_to_string(&encrypted).unwrap();
+        let decrypted_text = std::fs::read_to_string(&decrypted).unwrap();
+        for file in [plain, encrypted, decrypted] {
+            std::fs::remove_file(file).unwrap();
+        }
+        assert_eq!(Some(encrypted_text), vigenere_cipher(text, "key", true));
+        assert_eq!(decrypted_text, text);
     }
 }